@@ -5,44 +5,79 @@ use crate::forza::chunk::ChunkSelector;
 use crate::gui::*;
 use eframe::{egui, epi};
 
+use std::collections::HashMap;
 use std::fs::File;
 
+fn image_pixels(image: image::RgbaImage) -> ((usize, usize), Vec<egui::Color32>) {
+    let size = (image.width() as usize, image.height() as usize);
+    let pixels: Vec<_> = image
+        .pixels()
+        .map(|p| egui::Color32::from_rgba_unmultiplied(p[0], p[1], p[2], p[3]))
+        .collect();
+    (size, pixels)
+}
+
 macro_rules! load_image {
     ($path: literal) => {{
         let data = include_bytes!($path);
         let image = image::load_from_memory(data)
             .expect("Failed to load image")
             .to_rgba8();
-        let size = (image.width() as usize, image.height() as usize);
-        let pixels: Vec<_> = image
-            .pixels()
-            .map(|p| egui::Color32::from_rgba_unmultiplied(p[0], p[1], p[2], p[3]))
-            .collect();
-        (size, pixels)
+        image_pixels(image)
     }};
 }
 
+type MapTexture = (egui::Vec2, egui::TextureId);
+
 #[derive(Default)]
 pub struct App {
     control_panel: ControlPanel,
     chunk_panel: ChunkPanel,
+    compare_panel: ComparePanel,
+    dashboard_panel: DashboardPanel,
+    gg_panel: GgPanel,
+    histogram_panel: HistogramPanel,
+    lap_panel: LapPanel,
     map_panel: MapPanel,
     packet_panel: PacketPanel,
+    playback_panel: PlaybackPanel,
+    plot_panel: PlotPanel,
+    track_panel: TrackPanel,
     chunks: forza::Chunks,
+    tracks: forza::Tracks,
+    maps: forza::MapProfiles,
+    map_textures: HashMap<Option<String>, MapTexture>, // By image path, None for the embedded map
+    pending_map: Option<(usize, bool)>, // Map to show once its image can be loaded, if picked automatically
+    pending_image: Option<String>,      // Image to open before making a map of it
+    outline_key: Option<(forza::ChunkId, usize)>, // Chunk drawn without a map
     socket: forza::Socket,
+    live: bool,          // The socket wakes the interface up
+    unsaved_bests: bool, // Written with the other changes of the tracks, or on exit
     last_selection: Option<ChunkSelector>,
 }
 
 impl App {
     pub fn process(&mut self) {
         if !self.control_panel.is_record() {
-            // Clear non-recorded packets
-            self.socket.try_iter().last();
+            // Clear non-recorded packets, but keep the triggers up to date
+            let control_panel = &mut self.control_panel;
+            let dashboard_panel = &mut self.dashboard_panel;
+            self.socket.try_iter().for_each(|p| {
+                dashboard_panel.push(&p);
+                control_panel.want_packet(&p);
+            });
         } else {
-            // Filter-out non-race packets if we only record race data
-            let wanted_packets = self.socket.try_iter(); //.filter(|p| {
-                                                         //    !self.control_panel.want_next_race() || p.game_mode() == forza::GameMode::Race
-                                                         //});
+            self.chunks
+                .set_split_rules(self.control_panel.split_rules());
+
+            // Filter-out packets the recording mode is not interested in
+            let control_panel = &mut self.control_panel;
+            let dashboard_panel = &mut self.dashboard_panel;
+            let wanted_packets = self
+                .socket
+                .try_iter()
+                .inspect(|p| dashboard_panel.push(p))
+                .filter(|p| control_panel.want_packet(p));
             self.chunks.chunkify(wanted_packets);
 
             self.last_selection = None;
@@ -51,29 +86,242 @@ impl App {
         }
     }
 
+    fn handle_shortcuts(&mut self, ctx: &egui::CtxRef) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+
+        let input = ctx.input();
+        if input.modifiers.command && input.key_pressed(egui::Key::Z) {
+            match input.modifiers.shift {
+                true => self.chunk_panel.gen_event(chunk_panel::EventTypes::Redo),
+                false => self.chunk_panel.gen_event(chunk_panel::EventTypes::Undo),
+            }
+        } else if input.modifiers.command && input.key_pressed(egui::Key::Y) {
+            self.chunk_panel.gen_event(chunk_panel::EventTypes::Redo);
+        }
+    }
+
+    // Select an edited chunk, or the last one if it disappeared
+    fn select_chunk(&mut self, chunk_id: forza::ChunkId) {
+        let selection = match self.chunks.get(chunk_id) {
+            Some(_) => ChunkSelector(chunk_id, None),
+            None => self.chunks.last_chunk_selector(),
+        };
+
+        self.last_selection = Some(selection);
+        self.chunk_panel.set_selection(selection);
+        self.refresh_map();
+    }
+
+    fn refresh_map(&mut self) {
+        self.map_panel
+            .set_packets(self.chunk_panel.selected_packets(&self.chunks));
+        self.map_panel
+            .set_rewinds(&self.chunk_panel.selected_rewinds(&self.chunks));
+        self.map_panel
+            .set_reference(self.compare_panel.reference_packets(&self.chunks));
+        self.map_panel
+            .set_overlays(&Self::overlays(&self.chunk_panel, &self.chunks));
+
+        // Without a map of the place, the track is drawn from the laps
+        let packets = self.chunk_panel.selected_packets(&self.chunks);
+        if self.map_panel.auto_map() {
+            let game = self.maps.detect_game(packets);
+            if let Some(game) = game.filter(|game| *game != self.maps.game()) {
+                self.maps.set_game(game);
+                self.save_maps();
+            }
+        }
+        // A map of another game may span the same coordinates, it does not count
+        let covered = match (self.map_panel.auto_map(), self.maps.pick(packets)) {
+            (true, Some(index)) => {
+                if index != self.map_panel.map() {
+                    self.pending_map = Some((index, true));
+                }
+                true
+            }
+            (true, None) => false,
+            (false, _) => self.maps.get(self.map_panel.map()).is_some_and(|map| {
+                map.game == self.maps.game() && packets.iter().any(|p| map.contains(p.position()))
+            }),
+        };
+        let chunk_id = self.chunk_panel.get_selection().0;
+        let chunk = self.chunks.get(chunk_id).filter(|_| !covered);
+        let key = chunk.map(|chunk| (chunk_id, chunk.packets.len()));
+        // While recording, the outline is only traced again once the chunk grew enough
+        let stale = match (key, self.outline_key) {
+            (Some((id, len)), Some((old_id, old_len))) => {
+                id != old_id || len < old_len || len >= old_len + old_len / 10 + 100
+            }
+            (key, old_key) => key.is_some() != old_key.is_some(),
+        };
+        if stale {
+            self.outline_key = key;
+            self.map_panel
+                .set_outline(chunk.and_then(|chunk| chunk.outline()));
+        }
+    }
+
+    fn overlays<'a>(
+        chunk_panel: &ChunkPanel,
+        chunks: &'a forza::Chunks,
+    ) -> Vec<(String, &'a [forza::Packet])> {
+        chunk_panel
+            .overlays()
+            .iter()
+            .map(|s| (selection_label(chunks, *s), chunks.packets_of(*s)))
+            .filter(|(_, packets)| !packets.is_empty())
+            .collect()
+    }
+
+    // Keep a packet of the selection, layer 0, or of an overlay in the packet panel
+    fn pin_packet(&mut self, layer: usize, index: usize) {
+        let selection = self.chunk_panel.get_selection();
+        let layers: Vec<_> = std::iter::once((
+            selection_label(&self.chunks, selection),
+            self.chunks.packets_of(selection),
+        ))
+        .chain(Self::overlays(&self.chunk_panel, &self.chunks))
+        .collect();
+
+        if let Some((name, packets)) = layers.get(layer) {
+            if let Some(packet) = packets.get(index) {
+                let seconds =
+                    packet.timestamp_ms.saturating_sub(packets[0].timestamp_ms) as f32 / 1000.0;
+                let label = format!("{} at {}", name, format_time(seconds));
+                self.packet_panel.pin(label, packet);
+            }
+        }
+    }
+
+    fn save_maps(&self) {
+        if let Err(error) = self.maps.save() {
+            dialog::error_dialog("Failed to save the maps", &error.to_string());
+        }
+    }
+
+    fn load_map_texture(
+        &mut self,
+        path: &str,
+        frame: &mut epi::Frame<'_>,
+    ) -> image::ImageResult<MapTexture> {
+        let key = Some(path.to_owned());
+        if let Some(texture) = self.map_textures.get(&key) {
+            return Ok(*texture);
+        }
+
+        let (size, pixels) = image_pixels(image::open(path)?.to_rgba8());
+        let texture = frame
+            .tex_allocator()
+            .alloc_srgba_premultiplied(size, &pixels);
+        let texture = (egui::Vec2::new(size.0 as f32, size.1 as f32), texture);
+        self.map_textures.insert(key, texture);
+        Ok(texture)
+    }
+
+    // Make a map of an image, once it is known to open
+    fn add_map(&mut self, path: String, frame: &mut epi::Frame<'_>) {
+        if let Err(error) = self.load_map_texture(&path, frame) {
+            let title = format!("Failed to open {:}", &path);
+            dialog::error_dialog(&title, &error.to_string());
+            return;
+        }
+
+        let name = std::path::Path::new(&path)
+            .file_stem()
+            .and_then(|name| name.to_str())
+            .unwrap_or("Map")
+            .to_owned();
+        let profile = forza::MapProfile::new(name, self.maps.game(), path);
+        let index = self.maps.insert(profile);
+        self.save_maps();
+        self.apply_map(index, frame, false);
+    }
+
+    // Show a map, loading its image the first time. A map picked automatically
+    // is skipped without a word when its image is missing
+    fn apply_map(&mut self, index: usize, frame: &mut epi::Frame<'_>, picked: bool) {
+        let profile = match self.maps.get(index) {
+            Some(profile) => profile.clone(),
+            None => return,
+        };
+
+        let texture = match &profile.image {
+            Some(path) => self.load_map_texture(path, frame),
+            None => Ok(self.map_textures[&None]),
+        };
+        let (size, texture) = match texture {
+            Ok(texture) => texture,
+            Err(_) if picked => return,
+            Err(error) => {
+                let title = format!("Failed to open {:}", profile.image.unwrap_or_default());
+                dialog::error_dialog(&title, &error.to_string());
+                return;
+            }
+        };
+
+        // The size is kept to pick the map without loading its image
+        if let Some(profile) = self.maps.get_mut(index) {
+            if profile.size != (size.x, size.y) {
+                profile.size = (size.x, size.y);
+                self.save_maps();
+            }
+        }
+        self.map_panel.set_image(size, texture);
+        self.map_panel.set_map(index, &profile);
+    }
+
+    fn save_tracks(&mut self) {
+        self.unsaved_bests = false;
+        if let Err(error) = self.tracks.save() {
+            dialog::error_dialog("Failed to save the tracks", &error.to_string());
+        }
+    }
+
+    // Show the gates of the active track, and cut the laps at its start line
+    fn activate_track(&mut self, track_id: Option<forza::TrackId>) {
+        let track = track_id.and_then(|track_id| self.tracks.get(track_id));
+        let gate = track.and_then(|track| track.start).or(self.chunks.gate());
+
+        self.track_panel.set_active(track.and(track_id));
+        self.chunks.set_active_track(track.and(track_id));
+        self.map_panel
+            .set_sectors(track.map(|track| &track.sectors[..]));
+        if gate != self.chunks.gate() {
+            self.chunks.set_gate(gate);
+            self.map_panel.set_gate(gate);
+            self.select_chunk(self.chunk_panel.get_selection().0);
+        }
+    }
+
     fn load_file(&mut self, path: &str) {
-        match File::open(path).and_then(|mut f| forza::read_packets(&mut f)) {
-            Ok(packets) => {
+        match File::open(path).and_then(|mut f| forza::read_chunks(&mut f)) {
+            Ok(forza::FileContent::Packets(packets)) => {
                 self.last_selection = None;
+                self.chunks
+                    .set_split_rules(self.control_panel.split_rules());
                 self.chunks.chunkify(packets.into_iter());
             }
+            Ok(forza::FileContent::Chunks(chunks)) => {
+                self.last_selection = None;
+                for (chunk_id, chunk) in chunks {
+                    self.chunks.insert_with_id(chunk_id, chunk);
+                }
+            }
             Err(error) => {
                 dialog::error_dialog(&format!("Failed to open {:}", &path), &error.to_string())
             }
         }
     }
 
-    fn save_file(&self, path: &str) {
-        if let Err(error) = File::create(path).and_then(|mut f| {
-            self.chunks
-                .list()
-                .iter()
-                .try_for_each(|c| forza::write_packets(c.packets.iter(), &mut f))
-        }) {
-            dialog::error_dialog(
+    fn save_file(&mut self, path: &str) {
+        match File::create(path).and_then(|mut f| forza::write_chunks(self.chunks.iter(), &mut f)) {
+            Ok(_) => self.chunks.clear_history(),
+            Err(error) => dialog::error_dialog(
                 &format!("Failed to write to {:}", &path),
                 &error.to_string(),
-            )
+            ),
         }
     }
 }
@@ -101,8 +349,7 @@ impl EventHandler<chunk_panel::EventTypes> for App {
             chunk_panel::EventTypes::ChangeSelection(chunk_sel) => {
                 if Some(chunk_sel) != self.last_selection {
                     self.last_selection = Some(chunk_sel);
-                    self.map_panel
-                        .set_packets(self.chunk_panel.selected_packets(&self.chunks));
+                    self.refresh_map();
                 }
             }
             chunk_panel::EventTypes::RemoveChunk(chunk_sel) => {
@@ -112,10 +359,168 @@ impl EventHandler<chunk_panel::EventTypes> for App {
                 self.last_selection = None;
                 self.chunk_panel
                     .set_selection(self.chunks.last_chunk_selector());
-                self.map_panel
-                    .set_packets(self.chunk_panel.selected_packets(&self.chunks));
+                self.refresh_map();
+            }
+            chunk_panel::EventTypes::SplitChunk(chunk_id, packet_index) => {
+                self.chunks.split_chunk(chunk_id, packet_index);
+                self.select_chunk(chunk_id);
+            }
+            chunk_panel::EventTypes::MergeChunks(chunk_id) => {
+                self.chunks.merge_chunks(chunk_id);
+                self.select_chunk(chunk_id);
+            }
+            chunk_panel::EventTypes::TrimChunk(chunk_id, range) => {
+                self.chunks.trim_chunk(chunk_id, range);
+                self.select_chunk(chunk_id);
+            }
+            chunk_panel::EventTypes::DropIdle(chunk_id) => {
+                self.chunks.drop_idle(chunk_id);
+                self.select_chunk(chunk_id);
+            }
+            chunk_panel::EventTypes::SetMetadata(chunk_sel, metadata) => {
+                self.chunks.set_metadata(&chunk_sel, metadata);
+            }
+            chunk_panel::EventTypes::ChangeOverlays => self.refresh_map(),
+            chunk_panel::EventTypes::Undo => {
+                self.chunks.undo();
+                self.select_chunk(self.chunk_panel.get_selection().0);
+            }
+            chunk_panel::EventTypes::Redo => {
+                self.chunks.redo();
+                self.select_chunk(self.chunk_panel.get_selection().0);
+            }
+        }
+    }
+}
+
+impl EventHandler<lap_panel::EventTypes> for App {
+    fn generator(&mut self) -> &mut dyn EventGenerator<lap_panel::EventTypes> {
+        &mut self.lap_panel
+    }
+
+    fn handle(&mut self, event: lap_panel::EventTypes) {
+        match event {
+            lap_panel::EventTypes::SelectLap(chunk_sel) => {
+                self.chunk_panel.set_selection(chunk_sel);
+            }
+        }
+    }
+}
+
+impl EventHandler<compare_panel::EventTypes> for App {
+    fn generator(&mut self) -> &mut dyn EventGenerator<compare_panel::EventTypes> {
+        &mut self.compare_panel
+    }
+
+    fn handle(&mut self, event: compare_panel::EventTypes) {
+        match event {
+            compare_panel::EventTypes::ChangeReference => self.refresh_map(),
+        }
+    }
+}
+
+impl EventHandler<map_panel::EventTypes> for App {
+    fn generator(&mut self) -> &mut dyn EventGenerator<map_panel::EventTypes> {
+        &mut self.map_panel
+    }
+
+    fn handle(&mut self, event: map_panel::EventTypes) {
+        match event {
+            map_panel::EventTypes::SetGate(gate) => {
+                if let Some(track_id) = self.track_panel.active() {
+                    self.tracks.set_start(track_id, gate);
+                    self.save_tracks();
+                }
+                self.chunks.set_gate(gate);
+                self.map_panel.set_gate(gate);
+                self.select_chunk(self.chunk_panel.get_selection().0);
+            }
+            map_panel::EventTypes::ChangeColoring => self.refresh_map(),
+            map_panel::EventTypes::SetGame(game) => {
+                self.maps.set_game(game);
+                self.save_maps();
+                self.refresh_map();
+            }
+            map_panel::EventTypes::SelectMap(index) => self.pending_map = Some((index, false)),
+            map_panel::EventTypes::LoadMap(path) => self.pending_image = Some(path),
+            map_panel::EventTypes::RemoveMap(index) => {
+                self.maps.remove(index);
+                self.save_maps();
+                self.pending_map = Some((0, false));
+            }
+            map_panel::EventTypes::Calibrate(points) => {
+                let index = self.map_panel.map();
+                if let Some(profile) = self.maps.get_mut(index) {
+                    match profile.calibrate(&points) {
+                        Ok(fit_error) => {
+                            self.map_panel.set_fit_error(index, fit_error);
+                            self.save_maps();
+                            self.pending_map = Some((index, false));
+                        }
+                        Err(error) => dialog::error_dialog("Failed to calibrate the map", &error),
+                    }
+                }
+            }
+            map_panel::EventTypes::AddSector(gate) => {
+                let active = self.track_panel.active();
+                if let Some(track_id) = active {
+                    self.tracks.add_sector(track_id, gate);
+                    self.save_tracks();
+                    self.activate_track(active);
+                }
+            }
+            map_panel::EventTypes::SelectPackets(range) => self.chunk_panel.set_edit_range(range),
+            map_panel::EventTypes::PinPacket(layer, index) => self.pin_packet(layer, index),
+        }
+    }
+}
+
+impl EventHandler<track_panel::EventTypes> for App {
+    fn generator(&mut self) -> &mut dyn EventGenerator<track_panel::EventTypes> {
+        &mut self.track_panel
+    }
+
+    fn handle(&mut self, event: track_panel::EventTypes) {
+        let active = self.track_panel.active();
+        match event {
+            track_panel::EventTypes::SetActive(track_id) => self.activate_track(track_id),
+            track_panel::EventTypes::Create(name) => {
+                let mut track = forza::Track::new(name);
+                track.start = self.chunks.gate();
+                let track_id = self.tracks.insert(track);
+                self.activate_track(Some(track_id));
+            }
+            track_panel::EventTypes::Rename(track_id, name) => {
+                if let Some(track) = self.tracks.get_mut(track_id) {
+                    track.name = name;
+                }
+            }
+            track_panel::EventTypes::Remove(track_id) => {
+                self.tracks.remove(track_id);
+                self.activate_track(None);
+            }
+            track_panel::EventTypes::AssignToSelection(track_id) => {
+                let chunk_id = self.chunk_panel.get_selection().0;
+                self.chunks.set_track(chunk_id, track_id);
+            }
+            track_panel::EventTypes::SetReference(track_id) => {
+                let packets = self.chunk_panel.selected_packets(&self.chunks);
+                if let Some(track) = self.tracks.get_mut(track_id) {
+                    track.set_reference(packets);
+                    self.chunks.reset_identification();
+                }
+            }
+            track_panel::EventTypes::ClearSectors(track_id) => {
+                self.tracks.clear_sectors(track_id);
+                self.activate_track(active);
+            }
+            track_panel::EventTypes::ResetBests(track_id) => {
+                if let Some(track) = self.tracks.get_mut(track_id) {
+                    track.reset_bests();
+                }
             }
         }
+        self.save_tracks();
     }
 }
 
@@ -128,6 +533,12 @@ impl epi::App for App {
         true
     }
 
+    fn on_exit(&mut self) {
+        if self.unsaved_bests {
+            self.save_tracks();
+        }
+    }
+
     fn setup(
         &mut self,
         _ctx: &egui::CtxRef,
@@ -138,12 +549,32 @@ impl epi::App for App {
         let map = frame
             .tex_allocator()
             .alloc_srgba_premultiplied(size, &pixels);
-        self.map_panel
-            .set_image(egui::Vec2::new(size.0 as f32, size.1 as f32), map);
+        self.map_textures
+            .insert(None, (egui::Vec2::new(size.0 as f32, size.1 as f32), map));
+
+        match forza::Tracks::load() {
+            Ok(tracks) => self.tracks = tracks,
+            Err(error) => dialog::error_dialog("Failed to read the tracks", &error.to_string()),
+        }
+        match forza::MapProfiles::load() {
+            Ok(maps) => self.maps = maps,
+            Err(error) => dialog::error_dialog("Failed to read the maps", &error.to_string()),
+        }
+        self.apply_map(0, frame, false);
     }
 
-    fn update(&mut self, ctx: &egui::CtxRef, _frame: &mut epi::Frame<'_>) {
+    fn update(&mut self, ctx: &egui::CtxRef, frame: &mut epi::Frame<'_>) {
+        if self.dashboard_panel.is_live() != self.live {
+            self.live = self.dashboard_panel.is_live();
+            let signal = frame.repaint_signal();
+            let wake = move || signal.request_repaint();
+            self.socket
+                .set_wake(self.live.then(|| Box::new(wake) as Box<dyn Fn() + Send>));
+        }
+
         self.process();
+        self.chunks.identify_tracks(&self.tracks);
+        self.unsaved_bests |= self.chunks.update_sectors(&mut self.tracks);
 
         for file in &ctx.input().raw.dropped_files {
             if let Some(path) = file.path.as_ref().and_then(|p| p.to_str()) {
@@ -154,18 +585,96 @@ impl epi::App for App {
         self.control_panel.show(ctx);
         EventHandler::<control_panel::EventTypes>::handle_events(self);
 
-        self.chunk_panel.show(ctx, &self.chunks);
+        self.dashboard_panel.show(ctx);
+
+        self.track_panel.show(ctx, &self.tracks);
+        EventHandler::<track_panel::EventTypes>::handle_events(self);
+
+        self.chunk_panel.show(ctx, &self.chunks, &self.tracks);
+        self.handle_shortcuts(ctx);
         EventHandler::<chunk_panel::EventTypes>::handle_events(self);
+
+        self.lap_panel
+            .show(ctx, &self.chunks, self.chunk_panel.get_selection());
+        EventHandler::<lap_panel::EventTypes>::handle_events(self);
+
+        self.compare_panel
+            .show(ctx, &self.chunks, self.chunk_panel.get_selection());
+        EventHandler::<compare_panel::EventTypes>::handle_events(self);
         if Some(self.chunk_panel.get_selection()) != self.last_selection {
             self.last_selection = Some(self.chunk_panel.get_selection());
-            self.map_panel
-                .set_packets(self.chunk_panel.selected_packets(&self.chunks));
+            self.refresh_map();
         }
 
+        self.playback_panel
+            .show(ctx, &self.chunks, self.chunk_panel.get_selection());
+        // The plots follow the cursor of the map, or the playback one
         let selected_packets = self.chunk_panel.selected_packets(&self.chunks);
-        let hovered_packet = self.map_panel.hovered_packet(selected_packets);
+        let map_hovered = self
+            .map_panel
+            .hovered_packet()
+            .filter(|(layer, _)| *layer == 0)
+            .map(|(_, index)| index);
+        self.plot_panel
+            .set_cursor(map_hovered.or(self.playback_panel.cursor(selected_packets)));
+        self.plot_panel
+            .show(ctx, &self.chunks, self.chunk_panel.get_selection());
+        if let Some(index) = self.plot_panel.clicked() {
+            self.pin_packet(0, index);
+        }
+        self.histogram_panel
+            .show(ctx, &self.chunks, self.chunk_panel.get_selection());
+
+        let selected_packets = self.chunk_panel.selected_packets(&self.chunks);
+        let edit_range = self.chunk_panel.edit_range(&self.chunks);
+        match edit_range.len() < selected_packets.len() {
+            true => self.map_panel.set_highlight(&selected_packets[edit_range]),
+            false => self.map_panel.set_highlight(&[]),
+        }
+
+        let overlays = Self::overlays(&self.chunk_panel, &self.chunks);
+        let layers: Vec<_> = std::iter::once(selected_packets)
+            .chain(overlays.iter().map(|(_, packets)| *packets))
+            .collect();
+        let hovered = self.map_panel.hovered_packet();
+        let selection_name = selection_label(&self.chunks, self.chunk_panel.get_selection());
+        let hover_label = hovered.map(|(layer, _)| match layer {
+            0 => selection_name.clone(),
+            _ => overlays[layer - 1].0.clone(),
+        });
+        self.map_panel.set_hover_label(hover_label);
+
+        // The map follows the cursor of the plots, or the playback one
+        let plot_hovered = self.plot_panel.hovered();
+        let playback = self.playback_panel.cursor(selected_packets);
+        self.map_panel
+            .set_marker(plot_hovered.and_then(|index| selected_packets.get(index)));
+        self.map_panel
+            .set_car(playback.and_then(|index| selected_packets.get(index)));
+
+        let hovered_packet = hovered
+            .map(|(layer, index)| &layers[layer][index])
+            .or_else(|| {
+                plot_hovered
+                    .or(playback)
+                    .and_then(|i| selected_packets.get(i))
+            });
         self.packet_panel.show(ctx, hovered_packet);
+        self.map_panel.set_pins(self.packet_panel.pins());
 
-        self.map_panel.show(ctx);
+        let named_layers: Vec<_> = std::iter::once(selection_name)
+            .chain(overlays.iter().map(|(name, _)| name.clone()))
+            .zip(layers.iter().copied())
+            .collect();
+        self.gg_panel.show(ctx, &named_layers, hovered_packet);
+
+        self.map_panel.show(ctx, &self.maps);
+        EventHandler::<map_panel::EventTypes>::handle_events(self);
+        if let Some(path) = self.pending_image.take() {
+            self.add_map(path, frame);
+        }
+        if let Some((index, picked)) = self.pending_map.take() {
+            self.apply_map(index, frame, picked);
+        }
     }
 }
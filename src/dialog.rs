@@ -31,6 +31,22 @@ pub fn pick_file_dialog() -> Option<String> {
     }
 }
 
+pub fn pick_image_dialog() -> Option<String> {
+    if cfg!(target_os = "macos") {
+        tinyfiledialogs::open_file_dialog(
+            "Select a map image",
+            "",
+            Some((&["*.jpg", "*.jpeg"], "JPEG images")),
+        )
+    } else {
+        rfd::FileDialog::new()
+            .set_title("Select a map image")
+            .add_filter("JPEG images", &["jpg", "jpeg"])
+            .pick_file()
+            .and_then(|path| path.to_str().map(|s| s.to_owned()))
+    }
+}
+
 pub fn error_dialog(title: &str, description: &str) {
     if cfg!(target_os = "macos") {
         tinyfiledialogs::message_box_ok(title, description, tinyfiledialogs::MessageBoxIcon::Error);
@@ -6,3 +6,42 @@ pub use socket::*;
 
 pub mod chunk;
 pub use chunk::*;
+
+pub mod file;
+pub use file::*;
+
+pub mod history;
+pub use history::*;
+
+pub mod metadata;
+pub use metadata::*;
+
+pub mod gate;
+pub use gate::*;
+
+pub mod track;
+pub use track::*;
+
+pub mod summary;
+pub use summary::*;
+
+pub mod compare;
+pub use compare::*;
+
+pub mod channel;
+pub use channel::*;
+
+pub mod envelope;
+pub use envelope::*;
+
+pub mod distribution;
+pub use distribution::*;
+
+pub mod map_profile;
+pub use map_profile::*;
+
+pub mod outline;
+pub use outline::*;
+
+pub mod spatial;
+pub use spatial::*;
@@ -0,0 +1,184 @@
+use super::*;
+
+const GRAVITY: f32 = 9.81;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Wheel {
+    FrontLeft,
+    FrontRight,
+    RearLeft,
+    RearRight,
+}
+
+impl Wheel {
+    pub const ALL: [Wheel; 4] = [
+        Wheel::FrontLeft,
+        Wheel::FrontRight,
+        Wheel::RearLeft,
+        Wheel::RearRight,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Wheel::FrontLeft => "Front left",
+            Wheel::FrontRight => "Front right",
+            Wheel::RearLeft => "Rear left",
+            Wheel::RearRight => "Rear right",
+        }
+    }
+
+    pub fn of<T: Copy>(&self, stat: &TireStat<T>) -> T {
+        match self {
+            Wheel::FrontLeft => stat.front_left,
+            Wheel::FrontRight => stat.front_right,
+            Wheel::RearLeft => stat.rear_left,
+            Wheel::RearRight => stat.rear_right,
+        }
+    }
+}
+
+// A value of the packets, as plotted or used to colour the map
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Channel {
+    Speed,
+    Rpm,
+    Throttle,
+    Brake,
+    Clutch,
+    Steer,
+    Gear,
+    Power,
+    Torque,
+    Boost,
+    Elevation,
+    LateralG,
+    LongitudinalG,
+    CombinedG,
+    TireTemp,
+    SlipRatio,
+    SlipAngle,
+    CombinedSlip,
+    SuspensionTravel,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 19] = [
+        Channel::Speed,
+        Channel::Rpm,
+        Channel::Throttle,
+        Channel::Brake,
+        Channel::Clutch,
+        Channel::Steer,
+        Channel::Gear,
+        Channel::Power,
+        Channel::Torque,
+        Channel::Boost,
+        Channel::Elevation,
+        Channel::LateralG,
+        Channel::LongitudinalG,
+        Channel::CombinedG,
+        Channel::TireTemp,
+        Channel::SlipRatio,
+        Channel::SlipAngle,
+        Channel::CombinedSlip,
+        Channel::SuspensionTravel,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Channel::Speed => "Speed",
+            Channel::Rpm => "RPM",
+            Channel::Throttle => "Throttle",
+            Channel::Brake => "Brake",
+            Channel::Clutch => "Clutch",
+            Channel::Steer => "Steer",
+            Channel::Gear => "Gear",
+            Channel::Power => "Power",
+            Channel::Torque => "Torque",
+            Channel::Boost => "Boost",
+            Channel::Elevation => "Elevation",
+            Channel::LateralG => "Lateral g",
+            Channel::LongitudinalG => "Longitudinal g",
+            Channel::CombinedG => "Combined g",
+            Channel::TireTemp => "Tire temperature",
+            Channel::SlipRatio => "Slip ratio",
+            Channel::SlipAngle => "Slip angle",
+            Channel::CombinedSlip => "Combined slip",
+            Channel::SuspensionTravel => "Suspension travel",
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Channel::Speed => "km/h",
+            Channel::Rpm => "rpm",
+            Channel::Throttle | Channel::Brake | Channel::Clutch | Channel::Steer => "%",
+            Channel::Power => "kW",
+            Channel::Torque => "N·m",
+            Channel::Boost => "psi",
+            Channel::Elevation => "m",
+            Channel::LateralG | Channel::LongitudinalG | Channel::CombinedG => "g",
+            Channel::TireTemp => "°F",
+            _ => "",
+        }
+    }
+
+    pub fn is_per_wheel(&self) -> bool {
+        matches!(
+            self,
+            Channel::TireTemp
+                | Channel::SlipRatio
+                | Channel::SlipAngle
+                | Channel::CombinedSlip
+                | Channel::SuspensionTravel
+        )
+    }
+
+    // The four values of the channels measured on each wheel
+    pub fn tire_stat<'a>(&self, packet: &'a Packet) -> Option<&'a TireStat<f32>> {
+        match self {
+            Channel::TireTemp => Some(&packet.tire_temp),
+            Channel::SlipRatio => Some(&packet.tire_slip_ratio),
+            Channel::SlipAngle => Some(&packet.tire_slip_angle),
+            Channel::CombinedSlip => Some(&packet.tire_combined_slip),
+            Channel::SuspensionTravel => Some(&packet.normalized_suspension_travel),
+            _ => None,
+        }
+    }
+
+    pub fn wheel_value(&self, packet: &Packet, wheel: Wheel) -> Option<f32> {
+        self.tire_stat(packet).map(|stat| wheel.of(stat))
+    }
+
+    // Wheel channels give the average temperature, or the worst slip or travel
+    pub fn value(&self, packet: &Packet) -> f32 {
+        let p = packet;
+        match self {
+            Channel::Speed => p.speed * 3.6,
+            Channel::Rpm => p.current_engine_rpm,
+            Channel::Throttle => p.accel as f32 / 2.55,
+            Channel::Brake => p.brake as f32 / 2.55,
+            Channel::Clutch => p.clutch as f32 / 2.55,
+            Channel::Steer => p.steer as f32 / 1.27,
+            Channel::Gear => p.gear as f32,
+            Channel::Power => p.power / 1000.0,
+            Channel::Torque => p.torque,
+            Channel::Boost => p.boost,
+            Channel::Elevation => p.position.y,
+            Channel::LateralG => p.acceleration.x / GRAVITY,
+            Channel::LongitudinalG => p.acceleration.z / GRAVITY,
+            Channel::CombinedG => p.acceleration.x.hypot(p.acceleration.z) / GRAVITY,
+            Channel::TireTemp => Wheel::ALL.iter().map(|w| w.of(&p.tire_temp)).sum::<f32>() / 4.0,
+            Channel::SlipRatio
+            | Channel::SlipAngle
+            | Channel::CombinedSlip
+            | Channel::SuspensionTravel => {
+                let stat = self.tire_stat(p).unwrap();
+                Wheel::ALL
+                    .iter()
+                    .map(|w| w.of(stat).abs())
+                    .fold(0.0, f32::max)
+            }
+        }
+    }
+}
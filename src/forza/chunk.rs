@@ -6,85 +6,556 @@ pub type LapId = Option<u16>;
 #[derive(PartialEq, Default, Clone, Copy)]
 pub struct ChunkSelector(pub ChunkId, pub LapId);
 
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SplitReason {
+    Start,
+    GameMode,
+    CarChange,
+    PositionJump,
+    TimeGap,
+    RaceRestart,
+    Manual,
+}
+
+impl SplitReason {
+    pub fn description(&self) -> &'static str {
+        match self {
+            SplitReason::Start => "Start of the recording",
+            SplitReason::GameMode => "Switched between free roam and race",
+            SplitReason::CarChange => "Changed car",
+            SplitReason::PositionJump => "Teleported",
+            SplitReason::TimeGap => "Resumed after a pause",
+            SplitReason::RaceRestart => "Restarted the race",
+            SplitReason::Manual => "Split by hand",
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => SplitReason::GameMode,
+            2 => SplitReason::CarChange,
+            3 => SplitReason::PositionJump,
+            4 => SplitReason::TimeGap,
+            5 => SplitReason::RaceRestart,
+            6 => SplitReason::Manual,
+            _ => SplitReason::Start,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub struct SplitRules {
+    pub car_change: bool,
+    pub position_jump: Option<f32>, // meters
+    pub time_gap: Option<u32>,      // milliseconds
+    pub race_restart: bool,
+}
+
+impl Default for SplitRules {
+    fn default() -> Self {
+        Self {
+            car_change: true,
+            position_jump: Some(200.0),
+            time_gap: Some(30_000),
+            race_restart: true,
+        }
+    }
+}
+
+const REWIND_DISTANCE: f32 = 20.0; // From where the car was at the time it resumes from
+const REWIND_GAP_MS: u32 = 10_000; // Picking the moment to resume from takes a few seconds
+
+// A rewind sends the race timer backwards, but not back to the start. In free
+// roam there is no timer, but the distance traveled goes back the same way
+pub fn is_rewind(last: &Packet, current: &Packet) -> bool {
+    match current.game_mode() {
+        GameMode::Race => {
+            current.current_race_time < last.current_race_time && current.current_race_time >= 1.0
+        }
+        GameMode::FreeRoam => {
+            current.distance_traveled < last.distance_traveled && current.distance_traveled >= 1.0
+        }
+        GameMode::None => false,
+    }
+}
+
+impl SplitRules {
+    pub fn split_reason(&self, last: &Packet, current: &Packet) -> Option<SplitReason> {
+        if current.game_mode() != last.game_mode() {
+            return Some(SplitReason::GameMode);
+        }
+
+        if self.car_change && current.car_ordinal != last.car_ordinal {
+            return Some(SplitReason::CarChange);
+        }
+
+        if let Some(max_jump) = self.position_jump {
+            let (dx, dz) = (
+                current.position.x - last.position.x,
+                current.position.z - last.position.z,
+            );
+            if dx.hypot(dz) > max_jump {
+                return Some(SplitReason::PositionJump);
+            }
+        }
+
+        if let Some(max_gap) = self.time_gap {
+            let gap = current.timestamp_ms.checked_sub(last.timestamp_ms);
+            if gap.is_some_and(|gap| gap > max_gap) {
+                return Some(SplitReason::TimeGap);
+            }
+        }
+
+        // The race timer drops back to zero when the race is restarted
+        if self.race_restart
+            && current.game_mode() == GameMode::Race
+            && current.current_race_time < last.current_race_time
+            && current.current_race_time < 1.0
+        {
+            return Some(SplitReason::RaceRestart);
+        }
+
+        None
+    }
+}
+
 #[derive(Default)]
 pub struct Chunks {
-    chunks: std::collections::LinkedList<Chunk>,
+    chunks: std::collections::HashMap<ChunkId, Chunk>,
+    order: Vec<ChunkId>,
+    next_id: ChunkId,
+    rules: SplitRules,
+    history: History,
+    sealed: bool, // The last chunk was edited, recording must not extend it
+    gate: Option<Gate>,
+    track: Option<TrackId>, // Track of the chunks being recorded
 }
 
 impl Chunks {
+    pub fn set_split_rules(&mut self, rules: SplitRules) {
+        self.rules = rules;
+    }
+
+    pub fn gate(&self) -> Option<Gate> {
+        self.gate
+    }
+
+    // Cut the free roam chunks into laps, each time the car crosses the gate
+    pub fn set_gate(&mut self, gate: Option<Gate>) {
+        self.gate = gate;
+        self.chunks
+            .values_mut()
+            .for_each(|chunk| chunk.set_gate(gate));
+    }
+
+    pub fn set_active_track(&mut self, track: Option<TrackId>) {
+        self.track = track;
+    }
+
     pub fn chunkify(&mut self, packets: impl Iterator<Item = Packet>) {
-        if self.chunks.is_empty() {
-            self.chunks.push_back(Chunk::new());
+        if self.order.is_empty() {
+            self.insert(Chunk::new(SplitReason::Start));
         };
 
         for p in packets {
-            if p.game_mode() != self.last_game_mode() && !self.chunks.back().unwrap().is_empty() {
-                self.finalize_last_chunk();
+            if std::mem::take(&mut self.sealed) && self.last().is_some_and(|c| !c.is_empty()) {
+                self.finalize_last_chunk(SplitReason::Manual);
+            }
+
+            let (gate, track) = (self.gate, self.track);
+            let last_chunk = self.last_mut().unwrap();
+            if last_chunk.is_empty() {
+                last_chunk.gate = gate;
+                last_chunk.track = track;
             }
 
-            self.chunks.back_mut().unwrap().push(p);
+            // The car may land far from the last packet, the chunk truncates the
+            // abandoned timeline instead
+            let last_chunk = self.last().unwrap();
+            let rewind = last_chunk.rewind_point(&p).is_some();
+            if let Some(reason) = last_chunk
+                .packets
+                .last()
+                .and_then(|last| self.rules.split_reason(last, &p))
+                .filter(|reason| !rewind || *reason != SplitReason::PositionJump)
+            {
+                self.finalize_last_chunk(reason);
+            }
+
+            self.last_mut().unwrap().push(p);
         }
     }
 
-    pub fn finalize_last_chunk(&mut self) {
-        self.chunks.back_mut().unwrap().finalize();
-        self.chunks.push_back(Chunk::new());
+    pub fn finalize_last_chunk(&mut self, reason: SplitReason) {
+        self.last_mut().unwrap().finalize();
+        self.insert(Chunk::new(reason));
+    }
+
+    pub fn insert(&mut self, chunk: Chunk) -> ChunkId {
+        let chunk_id = self.next_id;
+        self.insert_with_id(chunk_id, chunk)
+    }
+
+    // Keep the requested ID unless it was already handed out in this session
+    pub fn insert_with_id(&mut self, chunk_id: ChunkId, mut chunk: Chunk) -> ChunkId {
+        let chunk_id = chunk_id.max(self.next_id);
+        self.next_id = chunk_id + 1;
+
+        if chunk.gate != self.gate {
+            chunk.set_gate(self.gate);
+        }
+
+        self.chunks.insert(chunk_id, chunk);
+        self.order.push(chunk_id);
+        chunk_id
     }
 
     pub fn remove_chunk(&mut self, chunk_selector: &ChunkSelector) {
-        match *chunk_selector {
-            ChunkSelector(chunk_id, None) => self._remove_chunk(chunk_id),
-            ChunkSelector(chunk_id, Some(lap_num)) => {
-                let chunk = self.chunks.iter_mut().nth(chunk_id).unwrap();
-                chunk.remove_lap(lap_num);
-                if chunk.packets.is_empty() {
-                    self._remove_chunk(chunk_id);
+        let ChunkSelector(chunk_id, lap_id) = *chunk_selector;
+        let name = match lap_id {
+            None => "Remove chunk".to_owned(),
+            Some(lap_num) => format!("Remove lap {}", lap_num + 1),
+        };
+        let exists = self
+            .get(chunk_id)
+            .is_some_and(|chunk| lap_id.is_none_or(|lap_num| chunk.lap_span(lap_num).is_some()));
+        if !exists {
+            return;
+        }
+
+        self.edit(&name, &[chunk_id], |chunks| match lap_id {
+            None => chunks._remove_chunk(chunk_id),
+            Some(lap_num) => {
+                if let Some(chunk) = chunks.chunks.get_mut(&chunk_id) {
+                    chunk.remove_lap(lap_num);
+                    if chunk.packets.is_empty() {
+                        chunks._remove_chunk(chunk_id);
+                    }
                 }
             }
+        });
+    }
+
+    pub fn split_chunk(&mut self, chunk_id: ChunkId, packet_index: usize) -> Option<ChunkId> {
+        let chunk = self.chunks.get(&chunk_id)?;
+        if packet_index == 0 || packet_index >= chunk.packets.len() {
+            return None;
         }
+
+        let tail_id = self.next_id;
+        self.edit("Split chunk", &[chunk_id], |chunks| {
+            let tail = chunks
+                .chunks
+                .get_mut(&chunk_id)
+                .unwrap()
+                .split_off(packet_index);
+            chunks.next_id += 1;
+            chunks.chunks.insert(tail_id, tail);
+
+            let position = chunks.position(chunk_id).unwrap();
+            chunks.order.insert(position + 1, tail_id);
+        });
+        Some(tail_id)
     }
 
-    fn _remove_chunk(&mut self, id: ChunkId) {
-        let mut split_list = self.chunks.split_off(id);
-        split_list.pop_front();
-        self.chunks.append(&mut split_list);
+    // Merge a chunk with the one following it
+    pub fn merge_chunks(&mut self, chunk_id: ChunkId) {
+        let next_id = match self.position(chunk_id).and_then(|p| self.order.get(p + 1)) {
+            Some(next_id) => *next_id,
+            None => return,
+        };
+
+        self.edit("Merge chunks", &[chunk_id, next_id], |chunks| {
+            if let Some(next) = chunks.chunks.remove(&next_id) {
+                chunks.order.retain(|&id| id != next_id);
+                if let Some(chunk) = chunks.chunks.get_mut(&chunk_id) {
+                    chunk.append(next);
+                }
+            }
+        });
     }
 
-    pub fn last_game_mode(&self) -> GameMode {
-        if self.chunks.back().is_some() {
-            self.chunks.back().unwrap().game_mode()
-        } else {
-            GameMode::None
+    pub fn trim_chunk(&mut self, chunk_id: ChunkId, range: std::ops::Range<usize>) {
+        self.edit("Trim chunk", &[chunk_id], |chunks| {
+            if let Some(chunk) = chunks.chunks.get_mut(&chunk_id) {
+                chunk.trim(range);
+                if chunk.is_empty() {
+                    chunks._remove_chunk(chunk_id);
+                }
+            }
+        });
+    }
+
+    pub fn drop_idle(&mut self, chunk_id: ChunkId) {
+        self.edit("Drop idle", &[chunk_id], |chunks| {
+            if let Some(chunk) = chunks.chunks.get_mut(&chunk_id) {
+                chunk.drop_idle();
+                if chunk.is_empty() {
+                    chunks._remove_chunk(chunk_id);
+                }
+            }
+        });
+    }
+
+    pub fn set_metadata(&mut self, chunk_selector: &ChunkSelector, metadata: Metadata) {
+        let ChunkSelector(chunk_id, lap_id) = *chunk_selector;
+        let renamed = self.get(chunk_id).is_some_and(|chunk| {
+            chunk.metadata_of(lap_id).map(|m| &m.name) != Some(&metadata.name)
+        });
+        let name = match renamed {
+            true => "Rename",
+            false => "Edit details",
+        };
+
+        self.edit_details(name, chunk_id, |chunk| chunk.set_metadata(lap_id, metadata));
+    }
+
+    pub fn set_track(&mut self, chunk_id: ChunkId, track: Option<TrackId>) {
+        self.edit_details("Set track", chunk_id, |chunk| chunk.set_track(track));
+    }
+
+    // Label the races with the track they were driven on, once a lap is done
+    pub fn identify_tracks(&mut self, tracks: &Tracks) {
+        let last_id = self.order.last().copied();
+        for (chunk_id, chunk) in self.chunks.iter_mut() {
+            let finished = Some(*chunk_id) != last_id || chunk.lap_count() > 1;
+            if chunk.identified || !finished || chunk.game_mode() != GameMode::Race {
+                continue;
+            }
+
+            chunk.identified = true;
+            if let Some(track_id) = tracks.identify(&chunk.packets) {
+                chunk.track = Some(track_id);
+            }
         }
     }
 
-    pub fn last_chunk_selector(&self) -> ChunkSelector {
-        self.generate_selector(self.chunks.len() - 1)
+    // Try again the races without a track, after the library changed
+    pub fn reset_identification(&mut self) {
+        self.chunks
+            .values_mut()
+            .filter(|chunk| chunk.track.is_none())
+            .for_each(|chunk| chunk.identified = false);
     }
 
-    pub fn game_mode_of(&self, chunk_selector: ChunkSelector) -> GameMode {
-        match chunk_selector {
-            ChunkSelector(chunk_id, _) => {
-                if let Some(chunk) = self.chunks.iter().nth(chunk_id) {
-                    chunk.game_mode()
-                } else {
-                    GameMode::None
+    // Refresh the sector times, returns if a track got a new best
+    pub fn update_sectors(&mut self, tracks: &mut Tracks) -> bool {
+        let mut improved = false;
+        for chunk in self.chunks.values_mut() {
+            let track = chunk.track.and_then(|track_id| tracks.get(track_id));
+            if !chunk.update_sectors(track, tracks.revision()) {
+                continue;
+            }
+
+            // Only the new times count, so the bests stay reset until a lap beats them
+            if let Some(track_id) = chunk.track {
+                let times: Vec<_> = chunk
+                    .lap_index
+                    .iter()
+                    .enumerate()
+                    .map(|(lap_idx, lap)| {
+                        let sector_times = chunk.sector_times.get(lap_idx).cloned();
+                        (chunk.lap_time(lap.0), sector_times.unwrap_or_default())
+                    })
+                    .collect();
+                chunk.submitted_times.resize(times.len(), (None, vec![]));
+                for (submitted, times) in chunk.submitted_times.iter_mut().zip(times) {
+                    if *submitted != times {
+                        improved |= tracks.update_bests(track_id, times.0, &times.1);
+                        *submitted = times;
+                    }
+                }
+            }
+        }
+        improved
+    }
+
+    pub fn undo_name(&self) -> Option<&str> {
+        self.history.undo_name()
+    }
+
+    pub fn redo_name(&self) -> Option<&str> {
+        self.history.redo_name()
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(Edit { name, snapshot }) = self.history.pop_undo() {
+            let snapshot = self.restore(snapshot);
+            self.history.push_redo(Edit { name, snapshot });
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(Edit { name, snapshot }) = self.history.pop_redo() {
+            let snapshot = self.restore(snapshot);
+            self.history.push_undo(Edit { name, snapshot });
+        }
+    }
+
+    // Forget the removed data, once the session is safely stored
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    // Run an edit, keeping a copy of the chunks it touches to undo it later
+    fn edit(&mut self, name: &str, chunk_ids: &[ChunkId], edit: impl FnOnce(&mut Self)) {
+        let order = self.order.clone();
+        let mut chunks: Vec<_> = chunk_ids
+            .iter()
+            .map(|chunk_id| (*chunk_id, self.chunks.get(chunk_id).cloned()))
+            .collect();
+
+        edit(self);
+
+        // The chunks created by the edit must disappear when undoing it
+        let created = self
+            .order
+            .iter()
+            .filter(|chunk_id| !order.contains(chunk_id))
+            .map(|chunk_id| (*chunk_id, None));
+        chunks.extend(created);
+
+        self.history.record(Edit {
+            name: name.to_owned(),
+            snapshot: Snapshot::Chunks { order, chunks },
+        });
+        self.sealed = true;
+    }
+
+    // Run an edit of the details of a chunk, recorded only if it changed them
+    fn edit_details(&mut self, name: &str, chunk_id: ChunkId, edit: impl FnOnce(&mut Chunk)) {
+        let chunk = match self.chunks.get_mut(&chunk_id) {
+            Some(chunk) => chunk,
+            None => return,
+        };
+        let details = chunk.details();
+        edit(chunk);
+        if chunk.details() != details {
+            self.history.record(Edit {
+                name: name.to_owned(),
+                snapshot: Snapshot::Details(chunk_id, details),
+            });
+        }
+    }
+
+    // Swap the chunks with the snapshot ones, returning the replaced state
+    fn restore(&mut self, snapshot: Snapshot) -> Snapshot {
+        let (order, chunks) = match snapshot {
+            Snapshot::Chunks { order, chunks } => (order, chunks),
+            Snapshot::Details(chunk_id, details) => {
+                let replaced = match self.chunks.get_mut(&chunk_id) {
+                    Some(chunk) => chunk.set_details(details),
+                    None => details,
+                };
+                return Snapshot::Details(chunk_id, replaced);
+            }
+        };
+
+        let mut replaced = Vec::with_capacity(chunks.len());
+        for (chunk_id, chunk) in chunks {
+            replaced.push((chunk_id, self.chunks.remove(&chunk_id)));
+            if let Some(mut chunk) = chunk {
+                if chunk.gate != self.gate {
+                    chunk.set_gate(self.gate);
                 }
+                self.chunks.insert(chunk_id, chunk);
             }
         }
+
+        // Chunks recorded after the edit stay at the end
+        let current = std::mem::take(&mut self.order);
+        self.order = order
+            .iter()
+            .chain(current.iter().filter(|id| !order.contains(id)))
+            .filter(|id| self.chunks.contains_key(id))
+            .copied()
+            .collect();
+        self.sealed = true;
+
+        Snapshot::Chunks {
+            order: current,
+            chunks: replaced,
+        }
+    }
+
+    fn position(&self, chunk_id: ChunkId) -> Option<usize> {
+        self.order.iter().position(|&id| id == chunk_id)
+    }
+
+    fn _remove_chunk(&mut self, id: ChunkId) {
+        self.chunks.remove(&id);
+        self.order.retain(|&chunk_id| chunk_id != id);
+    }
+
+    pub fn get(&self, chunk_id: ChunkId) -> Option<&Chunk> {
+        self.chunks.get(&chunk_id)
+    }
+
+    pub fn get_mut(&mut self, chunk_id: ChunkId) -> Option<&mut Chunk> {
+        self.chunks.get_mut(&chunk_id)
+    }
+
+    pub fn packets_of(&self, chunk_selector: ChunkSelector) -> &[Packet] {
+        let ChunkSelector(chunk_id, lap_id) = chunk_selector;
+        match (self.get(chunk_id), lap_id) {
+            (Some(chunk), Some(lap_num)) => chunk.lap_packets(lap_num),
+            (Some(chunk), None) => &chunk.packets,
+            (None, _) => &[],
+        }
+    }
+
+    pub fn last(&self) -> Option<&Chunk> {
+        self.order.last().and_then(|chunk_id| self.get(*chunk_id))
+    }
+
+    fn last_mut(&mut self) -> Option<&mut Chunk> {
+        let chunk_id = *self.order.last()?;
+        self.get_mut(chunk_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ChunkId, &Chunk)> {
+        self.order
+            .iter()
+            .map(move |chunk_id| (*chunk_id, &self.chunks[chunk_id]))
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    pub fn last_game_mode(&self) -> GameMode {
+        self.last()
+            .map(|chunk| chunk.game_mode())
+            .unwrap_or(GameMode::None)
+    }
+
+    pub fn last_chunk_selector(&self) -> ChunkSelector {
+        match self.order.last() {
+            Some(chunk_id) => self.generate_selector(*chunk_id),
+            None => ChunkSelector(self.next_id, None),
+        }
     }
 
-    pub fn list(&self) -> &std::collections::LinkedList<Chunk> {
-        &self.chunks
+    pub fn game_mode_of(&self, chunk_selector: ChunkSelector) -> GameMode {
+        match chunk_selector {
+            ChunkSelector(chunk_id, _) => self
+                .get(chunk_id)
+                .map(|chunk| chunk.game_mode())
+                .unwrap_or(GameMode::None),
+        }
     }
 
     fn generate_selector(&self, chunk_id: ChunkId) -> ChunkSelector {
-        let chunk = self.chunks.iter().nth(chunk_id);
+        let chunk = self.get(chunk_id);
         let mut lap_id: LapId = None;
 
         if let Some(chunk) = chunk {
             lap_id = match chunk.game_mode() {
-                GameMode::Race => Some(chunk.lap_count() - 1),
+                GameMode::Race => chunk.lap_index.last().map(|l| l.0),
                 _ => None,
             };
         }
@@ -93,27 +564,74 @@ impl Chunks {
     }
 }
 
+#[derive(Clone)]
 pub struct Lap(pub u16, pub usize, pub Option<usize>);
+
+#[derive(Clone)]
+pub struct Rewind {
+    pub timestamp_ms: u32,
+    pub lap_number: u16,
+    pub position: (f32, f32),
+    pub discarded: usize, // Number of packets dropped from the timeline
+}
+
+#[derive(Clone)]
 pub struct Chunk {
     pub packets: PacketVec,
     pub lap_index: Vec<Lap>,
+    pub rewinds: Vec<Rewind>,
+    pub split_reason: SplitReason,
+    pub metadata: Metadata,
+    pub lap_metadata: std::collections::BTreeMap<u16, Metadata>,
+    pub gate: Option<Gate>,
+    pub crossings: Vec<(Option<Crossing>, Option<Crossing>)>, // Gate crossings around each lap
+    pub track: Option<TrackId>,
+    pub sector_times: Vec<Vec<Option<f32>>>, // For each lap of the index
+    sectors_key: Option<(Option<TrackId>, u64, usize)>, // What the sector times were computed for
+    submitted_times: Vec<(Option<f32>, Vec<Option<f32>>)>, // Lap and sector times given to the track bests
+    identified: bool, // The track was looked up, or picked by hand
 }
 
 impl Chunk {
-    pub fn new() -> Self {
+    pub fn new(split_reason: SplitReason) -> Self {
         Chunk {
             packets: PacketVec::with_capacity(5 * 60 * 60),
             lap_index: vec![],
+            rewinds: vec![],
+            split_reason,
+            metadata: Metadata::default(),
+            lap_metadata: std::collections::BTreeMap::new(),
+            gate: None,
+            crossings: vec![],
+            track: None,
+            sector_times: vec![],
+            sectors_key: None,
+            submitted_times: vec![],
+            identified: false,
         }
     }
 
-    pub fn with_packets(packets: PacketVec) -> Self {
+    pub fn with_packets(packets: PacketVec, split_reason: SplitReason) -> Self {
         let mut lap_index = Vec::new();
         packets.iter().enumerate().for_each(|(packet_index, _)| {
             Self::update_index(&packets, &mut &mut lap_index, packet_index)
         });
 
-        Chunk { packets, lap_index }
+        Chunk {
+            packets,
+            lap_index,
+            rewinds: vec![],
+            split_reason,
+            metadata: Metadata::default(),
+            lap_metadata: std::collections::BTreeMap::new(),
+            gate: None,
+            crossings: vec![],
+            track: None,
+            sector_times: vec![],
+            sectors_key: None,
+            submitted_times: vec![],
+            identified: false,
+        }
     }
 
     pub fn finalize(&mut self) {
@@ -157,6 +675,12 @@ impl Chunk {
         if let Some((lap_idx, begin, end)) = self.lap_range(lap_num) {
             drop(self.packets.drain(begin..end));
             self.lap_index.remove(lap_idx);
+            if lap_idx < self.crossings.len() {
+                self.crossings.remove(lap_idx);
+            }
+            self.rewinds.retain(|r| r.lap_number != lap_num);
+            self.lap_metadata.remove(&lap_num);
+            self.sectors_key = None;
 
             let offset = end - begin;
             self.lap_index.iter_mut().skip(lap_idx).for_each(|l| {
@@ -166,9 +690,435 @@ impl Chunk {
         }
     }
 
+    pub fn metadata_of(&self, lap_id: LapId) -> Option<&Metadata> {
+        match lap_id {
+            None => Some(&self.metadata),
+            Some(lap_num) => self.lap_metadata.get(&lap_num),
+        }
+    }
+
+    pub fn set_metadata(&mut self, lap_id: LapId, metadata: Metadata) {
+        match lap_id {
+            None => self.metadata = metadata,
+            Some(lap_num) if metadata.is_empty() => drop(self.lap_metadata.remove(&lap_num)),
+            Some(lap_num) => drop(self.lap_metadata.insert(lap_num, metadata)),
+        }
+    }
+
+    // Whether the chunk, or one of its laps, matches a filter
+    pub fn matches(&self, filter: &str) -> bool {
+        self.metadata.matches(filter)
+            || self
+                .lap_index
+                .iter()
+                .any(|lap| self.lap_matches(lap.0, filter))
+    }
+
+    pub fn lap_matches(&self, lap_num: u16, filter: &str) -> bool {
+        self.lap_metadata
+            .get(&lap_num)
+            .map_or(filter.trim().is_empty(), |m| m.matches(filter))
+    }
+
+    pub fn lap_span(&self, lap_num: u16) -> Option<std::ops::Range<usize>> {
+        self.lap_range(lap_num).map(|(_, begin, end)| begin..end)
+    }
+
+    pub fn split_off(&mut self, packet_index: usize) -> Chunk {
+        let mut tail =
+            Chunk::with_packets(self.packets.split_off(packet_index), SplitReason::Manual);
+        tail.set_gate(self.gate);
+        self.rebuild_index();
+
+        let split_time = tail.packets.first().map(|p| p.timestamp_ms);
+        let (tail_rewinds, rewinds) = std::mem::take(&mut self.rewinds)
+            .into_iter()
+            .partition(|r| Some(r.timestamp_ms) >= split_time);
+        self.rewinds = rewinds;
+
+        let lap_metadata = self
+            .lap_metadata
+            .iter()
+            .filter(|(lap_num, _)| tail.lap_span(**lap_num).is_some())
+            .map(|(lap_num, metadata)| (*lap_num, metadata.clone()))
+            .collect();
+        self.retain_lap_metadata();
+
+        Chunk {
+            rewinds: tail_rewinds,
+            metadata: self.metadata.clone(),
+            lap_metadata,
+            track: self.track,
+            ..tail
+        }
+    }
+
+    pub fn append(&mut self, mut other: Chunk) {
+        // Another race numbers its laps from the start again, they follow ours instead
+        let new_race = match (self.packets.last(), other.packets.first()) {
+            (Some(last), Some(first)) => {
+                self.game_mode() == GameMode::Race
+                    && other.game_mode() == GameMode::Race
+                    && first.current_race_time < last.current_race_time
+            }
+            _ => false,
+        };
+        let first_lap = other.packets.iter().map(|p| p.lap_number).min();
+        let last_lap = self.packets.iter().map(|p| p.lap_number).max();
+        let mut offset = 0;
+        if let (true, Some(first_lap), Some(last_lap)) = (new_race, first_lap, last_lap) {
+            offset = (last_lap + 1).saturating_sub(first_lap);
+            other
+                .packets
+                .iter_mut()
+                .for_each(|p| p.lap_number += offset);
+            other
+                .rewinds
+                .iter_mut()
+                .for_each(|r| r.lap_number += offset);
+        }
+
+        self.packets.append(&mut other.packets);
+        self.rewinds.append(&mut other.rewinds);
+        // A lap split in two keeps the notes of both halves
+        for (lap_num, metadata) in other.lap_metadata {
+            self.lap_metadata
+                .entry(lap_num + offset)
+                .or_default()
+                .merge(metadata);
+        }
+        self.rebuild_index();
+    }
+
+    pub fn trim(&mut self, range: std::ops::Range<usize>) {
+        let range = range.start.min(self.packets.len())..range.end.min(self.packets.len());
+        drop(self.packets.drain(range.end..));
+        drop(self.packets.drain(..range.start));
+        self.retain_rewinds();
+        self.rebuild_index();
+        self.retain_lap_metadata();
+    }
+
+    // Remove the stretches where the car stands still for a few seconds
+    pub fn drop_idle(&mut self) {
+        const IDLE_SPEED: f32 = 0.5; // meters per second
+        const IDLE_DURATION: u32 = 5_000; // milliseconds
+
+        // Short stops are kept, like the ones at the finish line
+        let keep_short = |kept: &mut PacketVec, idle: &mut PacketVec| {
+            let idle_duration = match (idle.first(), idle.last()) {
+                (Some(first), Some(last)) => last.timestamp_ms.saturating_sub(first.timestamp_ms),
+                _ => 0,
+            };
+            if idle_duration < IDLE_DURATION {
+                kept.append(idle);
+            }
+            idle.clear();
+        };
+
+        let mut kept = PacketVec::with_capacity(self.packets.len());
+        let mut idle = PacketVec::new();
+        for p in self.packets.drain(..) {
+            if p.speed < IDLE_SPEED {
+                idle.push(p);
+                continue;
+            }
+
+            keep_short(&mut kept, &mut idle);
+            kept.push(p);
+        }
+        keep_short(&mut kept, &mut idle);
+
+        self.packets = kept;
+        self.retain_rewinds();
+        self.rebuild_index();
+        self.retain_lap_metadata();
+    }
+
+    fn retain_lap_metadata(&mut self) {
+        let lap_index = &self.lap_index;
+        self.lap_metadata
+            .retain(|lap_num, _| lap_index.iter().any(|l| l.0 == *lap_num));
+    }
+
+    fn retain_rewinds(&mut self) {
+        if let (Some(first), Some(last)) = (self.packets.first(), self.packets.last()) {
+            let (begin, end) = (first.timestamp_ms, last.timestamp_ms);
+            self.rewinds
+                .retain(|r| begin <= r.timestamp_ms && r.timestamp_ms <= end);
+        } else {
+            self.rewinds.clear();
+        }
+    }
+
+    pub fn rebuild_index(&mut self) {
+        self.lap_index.clear();
+        self.crossings.clear();
+        self.sectors_key = None;
+        for packet_index in 0..self.packets.len() {
+            self.update_lap_index(packet_index);
+        }
+    }
+
+    pub fn set_gate(&mut self, gate: Option<Gate>) {
+        self.gate = gate;
+        self.rebuild_index();
+    }
+
+    // Free roam laps are cut by the start/finish gate placed on the map
+    pub fn virtual_gate(&self) -> Option<Gate> {
+        self.gate.filter(|_| self.game_mode() == GameMode::FreeRoam)
+    }
+
+    pub fn virtual_lap_time(&self, lap_num: u16) -> Option<f32> {
+        let (lap_idx, _, _) = self.lap_range(lap_num)?;
+        match self.crossings.get(lap_idx)? {
+            (Some(start), Some(end)) => Some(((end.time_ms - start.time_ms) / 1000.0) as f32),
+            _ => None,
+        }
+    }
+
+    pub fn set_track(&mut self, track: Option<TrackId>) {
+        self.track = track;
+        self.identified = true;
+    }
+
+    pub fn details(&self) -> Details {
+        Details {
+            metadata: self.metadata.clone(),
+            lap_metadata: self.lap_metadata.clone(),
+            track: self.track,
+            identified: self.identified,
+        }
+    }
+
+    // Returns the replaced details
+    pub fn set_details(&mut self, details: Details) -> Details {
+        let replaced = self.details();
+        self.metadata = details.metadata;
+        self.lap_metadata = details.lap_metadata;
+        self.track = details.track;
+        self.identified = details.identified;
+        replaced
+    }
+
+    pub fn lap_time(&self, lap_num: u16) -> Option<f32> {
+        if self.virtual_gate().is_some() {
+            return self.virtual_lap_time(lap_num);
+        }
+
+        // The next lap starts with the time of the finished one
+        let (_, _, end) = self.lap_range(lap_num)?;
+        self.packets
+            .get(end)
+            .filter(|p| p.game_mode() == GameMode::Race)
+            .map(|p| p.last_lap)
+    }
+
+    // Finished laps follow the whole track, without the way to the grid
+    pub fn outline(&self) -> Option<Outline> {
+        let laps: Vec<_> = self
+            .lap_index
+            .iter()
+            .filter(|lap| self.lap_time(lap.0).is_some())
+            .map(|lap| self.lap_packets(lap.0))
+            .collect();
+        match laps.is_empty() {
+            true => Outline::new(&[&self.packets]),
+            false => Outline::new(&laps),
+        }
+    }
+
+    pub fn lap_summaries(&self) -> Vec<LapSummary> {
+        self.lap_index
+            .iter()
+            .map(|lap| LapSummary::new(lap.0, self.lap_packets(lap.0), self.lap_time(lap.0)))
+            .collect()
+    }
+
+    pub fn lap_sector_times(&self, lap_num: u16) -> Option<&[Option<f32>]> {
+        let (lap_idx, _, _) = self.lap_range(lap_num)?;
+        self.sector_times.get(lap_idx).map(|s| &s[..])
+    }
+
+    // The best time of each sector among the laps of the chunk
+    pub fn best_sector_times(&self) -> Vec<Option<f32>> {
+        let mut best_times: Vec<Option<f32>> = Vec::new();
+        for sector_times in &self.sector_times {
+            best_times.resize(sector_times.len().max(best_times.len()), None);
+            for (best, time) in best_times.iter_mut().zip(sector_times) {
+                if let Some(time) = *time {
+                    *best = Some(best.map_or(time, |best| best.min(time)));
+                }
+            }
+        }
+        best_times
+    }
+
+    // Returns if the sector times had to be computed again
+    pub fn update_sectors(&mut self, track: Option<&Track>, revision: u64) -> bool {
+        let key = Some((self.track, revision, self.packets.len()));
+        if key == self.sectors_key {
+            return false;
+        }
+        // Other gates reset the bests, all the laps are given again
+        if self.sectors_key.map(|k| (k.0, k.1)) != Some((self.track, revision)) {
+            self.submitted_times.clear();
+        }
+
+        // While recording, only the lap being driven can change. Any other edit
+        // of the packets resets the key
+        let kept = match self.sectors_key {
+            Some((track_id, rev, len))
+                if (track_id, rev) == (self.track, revision) && len < self.packets.len() =>
+            {
+                self.sector_times.len().saturating_sub(1)
+            }
+            _ => 0,
+        };
+        self.sectors_key = key;
+
+        match track.filter(|t| !t.sectors.is_empty()) {
+            Some(track) => {
+                let sector_times: Vec<_> = self
+                    .lap_index
+                    .iter()
+                    .enumerate()
+                    .skip(kept)
+                    .map(|(lap_idx, lap)| self.compute_sector_times(track, lap_idx, lap.0))
+                    .collect();
+                self.sector_times.truncate(kept);
+                self.sector_times.extend(sector_times);
+            }
+            None => self.sector_times.clear(),
+        }
+        true
+    }
+
+    fn compute_sector_times(
+        &self,
+        track: &Track,
+        lap_idx: usize,
+        lap_num: u16,
+    ) -> Vec<Option<f32>> {
+        let packets = self.lap_packets(lap_num);
+        let lap_time = self.lap_time(lap_num);
+
+        if self.virtual_gate().is_some() {
+            // The lap clock starts when crossing the start/finish line
+            match self.crossings.get(lap_idx).and_then(|c| c.0) {
+                Some(start) => track.sector_times(
+                    packets,
+                    |p| ((p.timestamp_ms as f64 - start.time_ms) / 1000.0) as f32,
+                    lap_time,
+                ),
+                None => vec![None; track.sector_count()],
+            }
+        } else if self.game_mode() == GameMode::Race {
+            track.sector_times(packets, |p| p.current_lap, lap_time)
+        } else {
+            vec![None; track.sector_count()]
+        }
+    }
+
+    fn update_lap_index(&mut self, packet_index: usize) {
+        match self.virtual_gate() {
+            Some(gate) => self.update_virtual_index(gate, packet_index),
+            None => Self::update_index(&self.packets, &mut self.lap_index, packet_index),
+        }
+    }
+
+    // Only the crossings in the direction of the first one start a new lap
+    fn update_virtual_index(&mut self, gate: Gate, packet_index: usize) {
+        match &self.packets[..=packet_index] {
+            [.., last, current] => {
+                let first_crossing = self.crossings.get(1).and_then(|c| c.0);
+                let crossing = gate
+                    .crossing(last, current)
+                    .filter(|c| first_crossing.is_none_or(|first| first.forward == c.forward));
+
+                if let Some(crossing) = crossing {
+                    if let Some(Lap(_, _, end)) = self.lap_index.last_mut() {
+                        *end = Some(packet_index);
+                    }
+                    if let Some((_, end)) = self.crossings.last_mut() {
+                        *end = Some(crossing);
+                    }
+
+                    let lap_num = self.lap_index.last().map_or(0, |l| l.0 + 1);
+                    self.lap_index.push(Lap(lap_num, packet_index, None));
+                    self.crossings.push((Some(crossing), None));
+                }
+            }
+            [_] => {
+                self.lap_index.push(Lap(0, packet_index, None));
+                self.crossings.push((None, None));
+            }
+            _ => {}
+        }
+    }
+
+    pub fn lap_rewinds(&self, lap_num: u16) -> impl Iterator<Item = &Rewind> {
+        self.rewinds.iter().filter(move |r| r.lap_number == lap_num)
+    }
+
     pub fn push(&mut self, packet: Packet) {
+        if let Some(resume_index) = self.rewind_point(&packet) {
+            self.rewind(resume_index, &packet);
+        }
+
         self.packets.push(packet);
-        Self::update_index(&self.packets, &mut self.lap_index, self.packets.len() - 1);
+        self.update_lap_index(self.packets.len() - 1);
+    }
+
+    // Resume after the last packet the rewind went back before
+    fn rewind_point(&self, packet: &Packet) -> Option<usize> {
+        let last = self.packets.last()?;
+        if !is_rewind(last, packet) {
+            return None;
+        }
+
+        let before = |p: &Packet| match packet.game_mode() {
+            GameMode::Race => p.current_race_time < packet.current_race_time,
+            _ => p.distance_traveled < packet.distance_traveled,
+        };
+        let resume_index = self.packets.iter().rposition(before).map_or(0, |i| i + 1);
+
+        // Fast travel or a new session lower the distance too, but only a rewind
+        // puts the car back where it was, right away
+        if packet.game_mode() == GameMode::FreeRoam {
+            let resumed = self.packets[resume_index.saturating_sub(1)].position();
+            let moved = (resumed.0 - packet.position().0).hypot(resumed.1 - packet.position().1);
+            let gap = packet.timestamp_ms.checked_sub(last.timestamp_ms);
+            if moved > REWIND_DISTANCE || gap.is_none_or(|gap| gap > REWIND_GAP_MS) {
+                return None;
+            }
+        }
+        Some(resume_index)
+    }
+
+    // Drop the abandoned timeline, so laps only keep what actually counted
+    fn rewind(&mut self, resume_index: usize, packet: &Packet) {
+        let discarded = self.packets.len() - resume_index;
+        self.packets.truncate(resume_index);
+
+        self.lap_index.retain(|l| l.1 < resume_index);
+        if let Some(Lap(_, _, end)) = self.lap_index.last_mut() {
+            *end = None;
+        }
+        self.crossings.truncate(self.lap_index.len());
+        if let Some((_, end)) = self.crossings.last_mut() {
+            *end = None;
+        }
+        // Keyed on the length, which the chunk may grow back to
+        self.sectors_key = None;
+
+        self.rewinds.push(Rewind {
+            timestamp_ms: packet.timestamp_ms,
+            lap_number: packet.lap_number,
+            position: packet.position(),
+            discarded,
+        });
     }
 
     fn update_index(packets: &[Packet], lap_index: &mut Vec<Lap>, packet_index: usize) {
@@ -186,3 +1136,373 @@ impl Chunk {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(timestamp_ms: u32, x: f32) -> Packet {
+        Packet {
+            timestamp_ms,
+            position: Vec3 {
+                x,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn race_packet(timestamp_ms: u32, race_time: f32) -> Packet {
+        Packet {
+            race_position: 1,
+            current_race_time: race_time,
+            ..packet(timestamp_ms, race_time)
+        }
+    }
+
+    #[test]
+    fn split_reasons() {
+        let rules = SplitRules::default();
+        let last = packet(1000, 0.0);
+        assert_eq!(rules.split_reason(&last, &packet(1016, 1.0)), None);
+
+        let other_car = Packet {
+            car_ordinal: 7,
+            ..packet(1016, 1.0)
+        };
+        assert_eq!(
+            rules.split_reason(&last, &other_car),
+            Some(SplitReason::CarChange)
+        );
+        assert_eq!(
+            rules.split_reason(&last, &packet(1016, 500.0)),
+            Some(SplitReason::PositionJump)
+        );
+        assert_eq!(
+            rules.split_reason(&last, &packet(60_000, 1.0)),
+            Some(SplitReason::TimeGap)
+        );
+        assert_eq!(
+            rules.split_reason(&last, &race_packet(1016, 0.0)),
+            Some(SplitReason::GameMode)
+        );
+        assert_eq!(
+            rules.split_reason(&race_packet(1000, 30.0), &race_packet(1016, 0.0)),
+            Some(SplitReason::RaceRestart)
+        );
+    }
+
+    #[test]
+    fn disabled_rules() {
+        let rules = SplitRules {
+            car_change: false,
+            position_jump: None,
+            time_gap: None,
+            race_restart: false,
+        };
+        let last = packet(1000, 0.0);
+        let other_car = Packet {
+            car_ordinal: 7,
+            ..packet(60_000, 500.0)
+        };
+        assert_eq!(rules.split_reason(&last, &other_car), None);
+        assert_eq!(
+            rules.split_reason(&race_packet(1000, 30.0), &race_packet(1016, 0.0)),
+            None
+        );
+    }
+
+    // Free roam around a circle of 100 packets a lap, crossing the gate at each lap
+    const GATE: Gate = Gate((90.0, 0.0), (110.0, 0.0));
+
+    fn circuit_packet(timestamp_ms: u32, step: u32) -> Packet {
+        let angle = (step as f32 + 0.5) / 100.0 * std::f32::consts::TAU;
+        Packet {
+            distance_traveled: step as f32 * 2.0 * std::f32::consts::PI,
+            position: Vec3 {
+                x: 100.0 * angle.cos(),
+                z: 100.0 * angle.sin(),
+                ..Default::default()
+            },
+            ..packet(timestamp_ms, 0.0)
+        }
+    }
+
+    #[test]
+    fn rewind_crossings() {
+        let mut chunk = Chunk::new(SplitReason::Start);
+        chunk.set_gate(Some(GATE));
+        (0..250).for_each(|i| chunk.push(circuit_packet(1000 + i * 100, i)));
+
+        // Back to the middle of the second lap, then around to the third one
+        (150..260).for_each(|i| chunk.push(circuit_packet(11_000 + i * 100, i)));
+        assert_eq!(chunk.rewinds.len(), 1);
+        assert_eq!(chunk.rewinds[0].discarded, 100);
+        assert_eq!(chunk.lap_index.len(), 3);
+        assert_eq!(chunk.crossings.len(), 3);
+        let lap_time = chunk.virtual_lap_time(1).unwrap();
+        assert!((lap_time - 20.0).abs() < 0.01, "{}", lap_time);
+        assert_eq!(chunk.virtual_lap_time(2), None);
+    }
+
+    #[test]
+    fn rewind_to_start() {
+        let mut chunk = Chunk::new(SplitReason::Start);
+        chunk.set_gate(Some(GATE));
+        (10..50).for_each(|i| chunk.push(circuit_packet(1000 + i * 100, i)));
+        chunk.update_sectors(None, 0);
+
+        let mut resumed = circuit_packet(6000, 10);
+        resumed.distance_traveled -= 1.0;
+        chunk.push(resumed);
+        assert_eq!(chunk.rewinds[0].discarded, 40);
+        assert_eq!(chunk.packets.len(), 1);
+        assert_eq!(chunk.lap_index.len(), 1);
+        assert_eq!(chunk.crossings.len(), 1);
+        assert!(chunk.sectors_key.is_none());
+    }
+
+    #[test]
+    fn sectors_while_recording() {
+        let track = Track {
+            sectors: vec![Gate((-110.0, 0.0), (-90.0, 0.0))],
+            ..Track::new("Circle".to_owned())
+        };
+        let mut chunk = Chunk::new(SplitReason::Start);
+        chunk.set_gate(Some(GATE));
+        for i in 0..260 {
+            chunk.push(circuit_packet(1000 + i * 100, i));
+            assert!(chunk.update_sectors(Some(&track), 0));
+        }
+        assert!(!chunk.update_sectors(Some(&track), 0));
+
+        // Only updating the last lap gives the same times as computing them all
+        let recorded = chunk.sector_times.clone();
+        chunk.sectors_key = None;
+        chunk.update_sectors(Some(&track), 0);
+        assert_eq!(recorded, chunk.sector_times);
+
+        let times: Vec<Vec<_>> = chunk
+            .sector_times
+            .iter()
+            .map(|laps| laps.iter().map(|t| t.map(|t| t.round())).collect())
+            .collect();
+        assert_eq!(
+            times,
+            [
+                vec![None, None],
+                vec![Some(5.0), Some(5.0)],
+                vec![Some(5.0), None]
+            ]
+        );
+    }
+
+    fn chunks_of(packets: Vec<Packet>) -> Chunks {
+        let mut chunks = Chunks::default();
+        chunks.chunkify(packets.into_iter());
+        chunks
+    }
+
+    #[test]
+    fn free_roam_rewind() {
+        let mut packets: Vec<_> = (0..100)
+            .map(|i| circuit_packet(1000 + i * 100, i))
+            .collect();
+        packets.push(circuit_packet(12_000, 50));
+        let chunks = chunks_of(packets);
+        assert_eq!(chunks.len(), 1);
+        let chunk = chunks.last().unwrap();
+        assert_eq!(chunk.rewinds.len(), 1);
+        assert_eq!(chunk.packets.len(), 51);
+    }
+
+    #[test]
+    fn distance_lowered_elsewhere() {
+        // Fast travel lands far from where the car was at that distance
+        let mut packets: Vec<_> = (0..100)
+            .map(|i| circuit_packet(1000 + i * 100, i))
+            .collect();
+        packets.push(Packet {
+            distance_traveled: 10.0,
+            ..packet(12_000, 5000.0)
+        });
+        let chunks = chunks_of(packets);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(
+            chunks.last().unwrap().split_reason,
+            SplitReason::PositionJump
+        );
+        assert_eq!(chunks.iter().next().unwrap().1.packets.len(), 100);
+
+        // A new session much later, even from the same place
+        let mut packets: Vec<_> = (0..100)
+            .map(|i| circuit_packet(1000 + i * 100, i))
+            .collect();
+        packets.push(circuit_packet(60_000, 50));
+        let chunks = chunks_of(packets);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks.last().unwrap().split_reason, SplitReason::TimeGap);
+        assert!(chunks.iter().all(|(_, chunk)| chunk.rewinds.is_empty()));
+    }
+
+    // A race of 50 packets a lap, moving unless stopped
+    fn lap_packet(timestamp_ms: u32, step: u32) -> Packet {
+        Packet {
+            lap_number: (step / 50) as u16,
+            speed: 10.0,
+            ..race_packet(timestamp_ms, 1.0 + step as f32 * 0.1)
+        }
+    }
+
+    fn race_chunk(length: u32) -> Chunk {
+        let mut chunk = Chunk::new(SplitReason::Start);
+        (0..length).for_each(|i| chunk.push(lap_packet(1000 + i * 100, i)));
+        chunk
+    }
+
+    fn named(name: &str) -> Metadata {
+        Metadata {
+            name: name.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    fn rewind_at(timestamp_ms: u32) -> Rewind {
+        Rewind {
+            timestamp_ms,
+            lap_number: 0,
+            position: (0.0, 0.0),
+            discarded: 1,
+        }
+    }
+
+    #[test]
+    fn split_and_merge() {
+        let mut chunk = race_chunk(100);
+        chunk.set_metadata(Some(1), named("second"));
+        chunk.rewinds.push(rewind_at(1000 + 80 * 100));
+        let mut chunks = Chunks::default();
+        let chunk_id = chunks.insert(chunk);
+        assert_eq!(chunks.split_chunk(chunk_id, 0), None);
+        assert_eq!(chunks.split_chunk(chunk_id, 100), None);
+
+        // The lap cut in two keeps its notes on both sides
+        let tail_id = chunks.split_chunk(chunk_id, 75).unwrap();
+        let ids: Vec<_> = chunks.iter().map(|(chunk_id, _)| chunk_id).collect();
+        assert_eq!(ids, [chunk_id, tail_id]);
+        let (head, tail) = (chunks.get(chunk_id).unwrap(), chunks.get(tail_id).unwrap());
+        assert_eq!((head.packets.len(), head.lap_count()), (75, 2));
+        assert_eq!((tail.packets.len(), tail.lap_count()), (25, 1));
+        assert_eq!(tail.lap_index[0].0, 1);
+        assert_eq!(tail.split_reason, SplitReason::Manual);
+        assert_eq!(head.lap_metadata[&1].name, "second");
+        assert_eq!(tail.lap_metadata[&1].name, "second");
+        assert!(head.rewinds.is_empty());
+        assert_eq!(tail.rewinds.len(), 1);
+
+        chunks.merge_chunks(chunk_id);
+        assert_eq!(chunks.len(), 1);
+        let chunk = chunks.get(chunk_id).unwrap();
+        assert_eq!((chunk.packets.len(), chunk.lap_count()), (100, 2));
+        assert_eq!(chunk.lap_packets(1).len(), 50);
+        assert_eq!(chunk.lap_metadata[&1].name, "second");
+        assert_eq!(chunk.rewinds.len(), 1);
+    }
+
+    #[test]
+    fn merge_restarted_race() {
+        // The laps of the second race follow the ones of the first
+        let mut chunk = race_chunk(100);
+        let mut restarted = Chunk::new(SplitReason::RaceRestart);
+        (0..100).for_each(|i| restarted.push(lap_packet(20_000 + i * 100, i)));
+        restarted.set_metadata(Some(0), named("third"));
+        chunk.append(restarted);
+
+        let laps: Vec<_> = chunk.lap_index.iter().map(|lap| lap.0).collect();
+        assert_eq!(laps, [0, 1, 2, 3]);
+        assert_eq!(chunk.lap_packets(3).len(), 50);
+        assert_eq!(chunk.lap_metadata[&2].name, "third");
+    }
+
+    #[test]
+    fn trim() {
+        let mut chunk = race_chunk(150);
+        chunk.set_metadata(Some(0), named("first"));
+        chunk.set_metadata(Some(1), named("second"));
+        chunk.rewinds.push(rewind_at(1000 + 10 * 100));
+        chunk.rewinds.push(rewind_at(1000 + 60 * 100));
+
+        chunk.trim(60..90);
+        assert_eq!(chunk.packets.len(), 30);
+        assert_eq!(chunk.lap_count(), 1);
+        assert_eq!((chunk.lap_index[0].0, chunk.lap_index[0].1), (1, 0));
+        assert_eq!(chunk.lap_metadata.keys().collect::<Vec<_>>(), [&1]);
+        assert_eq!(chunk.rewinds.len(), 1);
+
+        // Past the end only keeps what there is
+        chunk.trim(20..1000);
+        assert_eq!(chunk.packets.len(), 10);
+
+        // Nothing left removes the chunk
+        let mut chunks = Chunks::default();
+        let chunk_id = chunks.insert(chunk);
+        chunks.trim_chunk(chunk_id, 5..5);
+        assert!(chunks.is_empty());
+    }
+
+    #[test]
+    fn drop_idle() {
+        // Stops of 2.9 s, exactly 5 s, and 0.9 s at the end
+        let stopped = |i: u32| (20..50).contains(&i) || (70..121).contains(&i) || i >= 140;
+        let mut chunk = Chunk::new(SplitReason::Start);
+        for i in 0..150 {
+            let speed = if stopped(i) { 0.0 } else { 10.0 };
+            chunk.push(Packet {
+                speed,
+                ..lap_packet(1000 + i * 100, i)
+            });
+        }
+        chunk.set_metadata(Some(2), named("third"));
+
+        chunk.drop_idle();
+        assert_eq!(chunk.packets.len(), 150 - 51);
+        assert_eq!(chunk.packets[70].timestamp_ms, 1000 + 121 * 100);
+        assert_eq!(chunk.lap_count(), 3);
+        // The third lap started during the long stop
+        assert_eq!(chunk.lap_index[2].1, 70);
+        assert_eq!(chunk.lap_metadata[&2].name, "third");
+
+        let mut chunks = Chunks::default();
+        let mut parked = Chunk::new(SplitReason::Start);
+        (0..60).for_each(|i| parked.push(packet(1000 + i * 100, 0.0)));
+        let chunk_id = chunks.insert(parked);
+        chunks.drop_idle(chunk_id);
+        assert!(chunks.is_empty());
+    }
+
+    #[test]
+    fn race_rewind() {
+        let mut chunk = race_chunk(150);
+        chunk.set_metadata(Some(2), named("third"));
+
+        // Back to the race time of the 70th packet, in the second lap
+        chunk.push(lap_packet(20_000, 70));
+        assert_eq!(chunk.packets.len(), 71);
+        assert_eq!(chunk.rewinds.len(), 1);
+        assert_eq!(chunk.rewinds[0].discarded, 80);
+        assert_eq!(chunk.rewinds[0].lap_number, 1);
+        assert_eq!(chunk.lap_count(), 2);
+        assert!(chunk.lap_index[1].2.is_none());
+        assert_eq!(chunk.lap_packets(1).len(), 21);
+    }
+
+    #[test]
+    fn clock_going_back() {
+        // A restarted game sends smaller timestamps, it is not a pause
+        let rules = SplitRules::default();
+        assert_eq!(
+            rules.split_reason(&packet(60_000, 0.0), &packet(1000, 1.0)),
+            None
+        );
+    }
+}
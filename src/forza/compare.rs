@@ -0,0 +1,112 @@
+use super::*;
+
+const STEP: f32 = 2.0; // meters between two samples
+
+#[derive(Clone, Copy, Default)]
+pub struct Sample {
+    pub time: f32, // seconds since the start of the lap
+    pub speed: f32,
+    pub accel: u8,
+    pub brake: u8,
+    pub gear: u8,
+}
+
+// Two laps resampled at the same distances along the track
+#[derive(Default)]
+pub struct Comparison {
+    pub distance: Vec<f32>,
+    pub reference: Vec<Sample>,
+    pub other: Vec<Sample>,
+    pub delta: Vec<f32>, // Time lost by the other lap, in seconds
+}
+
+impl Comparison {
+    pub fn new(reference: &[Packet], other: &[Packet]) -> Self {
+        let reference = Self::resample(reference);
+        let other = Self::resample(other);
+        let len = reference.len().min(other.len());
+
+        let delta = (0..len)
+            .map(|i| other[i].time - reference[i].time)
+            .collect();
+        Comparison {
+            distance: (0..len).map(|i| i as f32 * STEP).collect(),
+            reference: reference[..len].to_vec(),
+            other: other[..len].to_vec(),
+            delta,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.distance.is_empty()
+    }
+
+    // Distance along the lap of each packet
+    pub fn distances(packets: &[Packet]) -> Vec<f32> {
+        let (first, last) = match (packets.first(), packets.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return vec![],
+        };
+
+        // The odometer of the game is the most precise, when it is counting
+        if last.distance_traveled > first.distance_traveled {
+            return packets
+                .iter()
+                .map(|p| (p.distance_traveled - first.distance_traveled).max(0.0))
+                .collect();
+        }
+
+        let mut length = 0.0;
+        let mut last_position = first.position();
+        packets
+            .iter()
+            .map(|p| {
+                let position = p.position();
+                length += (position.0 - last_position.0).hypot(position.1 - last_position.1);
+                last_position = position;
+                length
+            })
+            .collect()
+    }
+
+    fn resample(packets: &[Packet]) -> Vec<Sample> {
+        let distances = Self::distances(packets);
+        let (first, length) = match (packets.first(), distances.last()) {
+            (Some(first), Some(length)) => (first, *length),
+            _ => return vec![],
+        };
+        let time_of =
+            |p: &Packet| p.timestamp_ms.saturating_sub(first.timestamp_ms) as f32 / 1000.0;
+
+        let mut samples = Vec::with_capacity((length / STEP) as usize + 1);
+        let mut index = 0;
+        for step in 0..=(length / STEP) as usize {
+            let distance = step as f32 * STEP;
+            while index + 2 < packets.len() && distances[index + 1] < distance {
+                index += 1;
+            }
+
+            let (before, after) = (
+                &packets[index],
+                &packets[(index + 1).min(packets.len() - 1)],
+            );
+            let span = distances[(index + 1).min(packets.len() - 1)] - distances[index];
+            let t = match span > 0.0 {
+                true => ((distance - distances[index]) / span).clamp(0.0, 1.0),
+                false => 0.0,
+            };
+            let lerp = |a: f32, b: f32| a + t * (b - a);
+
+            // The pedals and gear are not interpolated
+            let nearest = if t < 0.5 { before } else { after };
+            samples.push(Sample {
+                time: lerp(time_of(before), time_of(after)),
+                speed: lerp(before.speed, after.speed),
+                accel: nearest.accel,
+                brake: nearest.brake,
+                gear: nearest.gear,
+            });
+        }
+        samples
+    }
+}
@@ -0,0 +1,68 @@
+// The values taken by a channel, sorted to read their percentiles
+#[derive(Default)]
+pub struct Distribution {
+    values: Vec<f32>,
+    mean: f32,
+}
+
+impl Distribution {
+    pub fn new(mut values: Vec<f32>) -> Self {
+        values.retain(|v| v.is_finite());
+        values.sort_by(|a, b| a.total_cmp(b));
+        let mean = match values.is_empty() {
+            true => 0.0,
+            false => values.iter().sum::<f32>() / values.len() as f32,
+        };
+        Self { values, mean }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn min(&self) -> f32 {
+        self.values.first().cloned().unwrap_or_default()
+    }
+
+    pub fn max(&self) -> f32 {
+        self.values.last().cloned().unwrap_or_default()
+    }
+
+    pub fn mean(&self) -> f32 {
+        self.mean
+    }
+
+    // Interpolated between the two closest values, for a part between 0 and 1
+    pub fn percentile(&self, part: f32) -> f32 {
+        if self.values.is_empty() {
+            return 0.0;
+        }
+        let position = part.clamp(0.0, 1.0) * (self.values.len() - 1) as f32;
+        let index = position as usize;
+        let next = (index + 1).min(self.values.len() - 1);
+        let t = position - index as f32;
+        self.values[index] + t * (self.values[next] - self.values[index])
+    }
+
+    // Number of values in each of the bins splitting the range
+    pub fn histogram(&self, (min, max): (f32, f32), bins: usize) -> Vec<usize> {
+        let mut counts = vec![0; bins];
+        if bins == 0 {
+            return counts;
+        }
+
+        let width = (max - min) / bins as f32;
+        for value in &self.values {
+            let bin = match width > 0.0 {
+                true => ((value - min) / width) as usize,
+                false => 0,
+            };
+            counts[bin.min(bins - 1)] += 1;
+        }
+        counts
+    }
+}
@@ -0,0 +1,26 @@
+use std::f32::consts::PI;
+
+const SECTORS: usize = 36; // Directions of the envelope, every 10°
+
+// Outline of a cloud of points around the origin, such as the g-forces of a lap.
+// In each direction, keeps the radius reached by the given part of the points
+pub fn envelope(points: &[(f32, f32)], percentile: f32) -> Vec<(f32, f32)> {
+    let mut radii = vec![Vec::new(); SECTORS];
+    for (x, y) in points {
+        let angle = y.atan2(*x) + PI;
+        let sector = (angle / (2.0 * PI) * SECTORS as f32) as usize % SECTORS;
+        radii[sector].push(x.hypot(*y));
+    }
+
+    radii
+        .iter_mut()
+        .enumerate()
+        .filter(|(_, radii)| !radii.is_empty())
+        .map(|(sector, radii)| {
+            radii.sort_by(|a, b| a.total_cmp(b));
+            let index = ((radii.len() - 1) as f32 * percentile.clamp(0.0, 1.0)).round() as usize;
+            let angle = (sector as f32 + 0.5) / SECTORS as f32 * 2.0 * PI - PI;
+            (radii[index] * angle.cos(), radii[index] * angle.sin())
+        })
+        .collect()
+}
@@ -0,0 +1,350 @@
+use std::io::{Read, Write};
+
+use super::*;
+
+// Files starting with this header store chunk records, older files are a
+// bare stream of packets.
+const MAGIC: &[u8; 3] = b"FTM";
+const VERSION: u8 = 4;
+const MAX_RESERVED: usize = 1 << 16; // Counts read from a corrupt file must not exhaust the memory
+
+// Where the settings shared by every session are stored
+pub fn config_path(file_name: &str) -> Option<std::path::PathBuf> {
+    let home = std::env::var_os("APPDATA").or_else(|| std::env::var_os("HOME"))?;
+    let mut path = std::path::PathBuf::from(home);
+    path.push(".forzanalyst");
+    path.push(file_name);
+    Some(path)
+}
+
+pub enum FileContent {
+    Packets(PacketVec),
+    Chunks(Vec<(ChunkId, Chunk)>),
+}
+
+pub fn write_chunks<'a>(
+    chunks: impl Iterator<Item = (ChunkId, &'a Chunk)>,
+    output: &mut impl Write,
+) -> std::io::Result<()> {
+    let output = std::io::BufWriter::new(output);
+    let mut output = zstd::Encoder::new(output, 0)?;
+
+    output.write_all(MAGIC)?;
+    output.write_all(&[VERSION])?;
+
+    let mut chunk_count = 0;
+    for (chunk_id, chunk) in chunks.filter(|(_, c)| !c.is_empty()) {
+        chunk_count += 1;
+        write_chunk(&mut output, chunk_id, chunk)?;
+    }
+    output.finish().and_then(|mut w| w.flush())?;
+
+    println!("Chunks written: {}", chunk_count);
+    Ok(())
+}
+
+pub fn read_chunks(input: &mut impl Read) -> std::io::Result<FileContent> {
+    let input = std::io::BufReader::new(input);
+    let mut input = zstd::Decoder::new(input)?;
+
+    let mut header = [0u8; 4];
+    input.read_exact(&mut header)?;
+    if &header[..3] != MAGIC {
+        let mut input = (&header[..]).chain(input);
+        return read_packet_stream(&mut input).map(FileContent::Packets);
+    }
+
+    let version = header[3];
+    if !(2..=VERSION).contains(&version) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Unsupported file version {}", version),
+        ));
+    }
+
+    let mut chunks = Vec::new();
+    while let Some(record) = read_chunk(&mut input, version)? {
+        chunks.push(record);
+    }
+
+    println!("Chunks read: {}", chunks.len());
+    Ok(FileContent::Chunks(chunks))
+}
+
+fn write_chunk(output: &mut impl Write, chunk_id: ChunkId, chunk: &Chunk) -> std::io::Result<()> {
+    output.write_all(&(chunk_id as u64).to_le_bytes())?;
+    output.write_all(&[chunk.split_reason as u8])?;
+
+    output.write_all(&(chunk.rewinds.len() as u32).to_le_bytes())?;
+    for rewind in &chunk.rewinds {
+        output.write_all(&rewind.timestamp_ms.to_le_bytes())?;
+        output.write_all(&rewind.lap_number.to_le_bytes())?;
+        output.write_all(&rewind.position.0.to_le_bytes())?;
+        output.write_all(&rewind.position.1.to_le_bytes())?;
+        output.write_all(&(rewind.discarded as u64).to_le_bytes())?;
+    }
+
+    write_metadata(output, &chunk.metadata)?;
+    output.write_all(&(chunk.lap_metadata.len() as u32).to_le_bytes())?;
+    for (lap_num, metadata) in &chunk.lap_metadata {
+        output.write_all(&lap_num.to_le_bytes())?;
+        write_metadata(output, metadata)?;
+    }
+
+    let track_id = chunk.track.map_or(u64::MAX, |track_id| track_id as u64);
+    output.write_all(&track_id.to_le_bytes())?;
+
+    output.write_all(&(chunk.packets.len() as u64).to_le_bytes())?;
+    for packet in &chunk.packets {
+        output.write_all(packet.as_buf())?;
+    }
+
+    Ok(())
+}
+
+fn read_chunk(input: &mut impl Read, version: u8) -> std::io::Result<Option<(ChunkId, Chunk)>> {
+    let chunk_id = match read_u64(input) {
+        Ok(chunk_id) => chunk_id as ChunkId,
+        Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    let split_reason = SplitReason::from_u8(read_u8(input)?);
+
+    let rewind_count = read_u32(input)?;
+    let mut rewinds = Vec::with_capacity((rewind_count as usize).min(MAX_RESERVED));
+    for _ in 0..rewind_count {
+        rewinds.push(Rewind {
+            timestamp_ms: read_u32(input)?,
+            lap_number: read_u16(input)?,
+            position: (read_f32(input)?, read_f32(input)?),
+            discarded: read_u64(input)? as usize,
+        });
+    }
+
+    // Metadata were introduced with the version 3
+    let mut metadata = Metadata::default();
+    let mut lap_metadata = std::collections::BTreeMap::new();
+    if version >= 3 {
+        metadata = read_metadata(input)?;
+        for _ in 0..read_u32(input)? {
+            let lap_num = read_u16(input)?;
+            lap_metadata.insert(lap_num, read_metadata(input)?);
+        }
+    }
+
+    // Tracks were introduced with the version 4
+    let mut track = None;
+    if version >= 4 {
+        track = Some(read_u64(input)?)
+            .filter(|track_id| *track_id != u64::MAX)
+            .map(|track_id| track_id as TrackId);
+    }
+
+    let packet_count = read_u64(input)? as usize;
+    let mut packets = PacketVec::with_capacity(packet_count.min(MAX_RESERVED));
+    for _ in 0..packet_count {
+        let mut packet = Packet::default();
+        input.read_exact(packet.as_buf_mut())?;
+        packets.push(packet);
+    }
+
+    let mut chunk = Chunk::with_packets(packets, split_reason);
+    chunk.rewinds = rewinds;
+    chunk.metadata = metadata;
+    chunk.lap_metadata = lap_metadata;
+    if track.is_some() {
+        chunk.set_track(track);
+    }
+    Ok(Some((chunk_id, chunk)))
+}
+
+fn write_metadata(output: &mut impl Write, metadata: &Metadata) -> std::io::Result<()> {
+    write_string(output, &metadata.name)?;
+    write_string(output, &metadata.notes)?;
+    write_string(output, &metadata.driver)?;
+    write_string(output, &metadata.setup)?;
+
+    output.write_all(&(metadata.tags.len() as u32).to_le_bytes())?;
+    metadata
+        .tags
+        .iter()
+        .try_for_each(|tag| write_string(output, tag))
+}
+
+fn read_metadata(input: &mut impl Read) -> std::io::Result<Metadata> {
+    let mut metadata = Metadata {
+        name: read_string(input)?,
+        notes: read_string(input)?,
+        driver: read_string(input)?,
+        setup: read_string(input)?,
+        tags: vec![],
+    };
+
+    for _ in 0..read_u32(input)? {
+        metadata.tags.push(read_string(input)?);
+    }
+    Ok(metadata)
+}
+
+pub(super) fn write_string(output: &mut impl Write, string: &str) -> std::io::Result<()> {
+    output.write_all(&(string.len() as u32).to_le_bytes())?;
+    output.write_all(string.as_bytes())
+}
+
+pub(super) fn read_string(input: &mut impl Read) -> std::io::Result<String> {
+    let len = read_u32(input)? as u64;
+    let mut buf = Vec::new();
+    input.take(len).read_to_end(&mut buf)?;
+    if buf.len() as u64 != len {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(buf).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+fn read_packet_stream(input: &mut impl Read) -> std::io::Result<PacketVec> {
+    let mut packets = PacketVec::with_capacity(1024);
+    loop {
+        let mut packet = Packet::default();
+        if let Err(error) = input.read_exact(packet.as_buf_mut()) {
+            match error.kind() {
+                std::io::ErrorKind::UnexpectedEof => break,
+                _ => return Err(error),
+            }
+        };
+        packets.push(packet);
+    }
+
+    println!("Packets read: {}", packets.len());
+    Ok(packets)
+}
+
+macro_rules! read_le {
+    ($name: ident, $type: ty) => {
+        pub(super) fn $name(input: &mut impl Read) -> std::io::Result<$type> {
+            let mut buf = [0u8; std::mem::size_of::<$type>()];
+            input.read_exact(&mut buf)?;
+            Ok(<$type>::from_le_bytes(buf))
+        }
+    };
+}
+
+read_le!(read_u8, u8);
+read_le!(read_u16, u16);
+read_le!(read_u32, u32);
+read_le!(read_u64, u64);
+read_le!(read_f32, f32);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(timestamp_ms: u32, x: f32) -> Packet {
+        Packet {
+            timestamp_ms,
+            position: Vec3 {
+                x,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn read_back(content: &[u8]) -> std::io::Result<FileContent> {
+        let compressed = zstd::encode_all(content, 0).unwrap();
+        read_chunks(&mut std::io::Cursor::new(compressed))
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut chunk = Chunk::with_packets(
+            (0..3).map(|i| packet(i * 16, i as f32)).collect(),
+            SplitReason::CarChange,
+        );
+        chunk.rewinds.push(Rewind {
+            timestamp_ms: 16,
+            lap_number: 1,
+            position: (1.0, 2.0),
+            discarded: 5,
+        });
+        chunk.metadata.name = "Practice".to_owned();
+        chunk.metadata.tags.push("wet".to_owned());
+        chunk.lap_metadata.entry(1).or_default().notes = "Spun".to_owned();
+        chunk.track = Some(3);
+
+        let mut file = Vec::new();
+        write_chunks([(7, &chunk)].into_iter(), &mut file).unwrap();
+        let chunks = match read_chunks(&mut file.as_slice()).unwrap() {
+            FileContent::Chunks(chunks) => chunks,
+            FileContent::Packets(_) => panic!("Read as a packet stream"),
+        };
+
+        assert_eq!(chunks.len(), 1);
+        let (chunk_id, read) = &chunks[0];
+        assert_eq!(*chunk_id, 7);
+        assert_eq!(read.split_reason, SplitReason::CarChange);
+        let timestamps: Vec<_> = read.packets.iter().map(|p| p.timestamp_ms).collect();
+        assert_eq!(timestamps, [0, 16, 32]);
+        assert_eq!(read.packets[2].position.x, 2.0);
+        assert_eq!(read.rewinds.len(), 1);
+        assert_eq!(read.rewinds[0].position, (1.0, 2.0));
+        assert_eq!(read.rewinds[0].discarded, 5);
+        assert!(read.metadata == chunk.metadata);
+        assert!(read.lap_metadata == chunk.lap_metadata);
+        assert_eq!(read.track, Some(3));
+    }
+
+    #[test]
+    fn older_versions() {
+        // Version 1 is a bare stream of packets
+        let stream: Vec<u8> = [packet(0, 1.0), packet(16, 2.0)]
+            .iter()
+            .flat_map(|p| p.as_buf().to_vec())
+            .collect();
+        match read_back(&stream).unwrap() {
+            FileContent::Packets(packets) => assert_eq!(packets.len(), 2),
+            FileContent::Chunks(_) => panic!("Read as chunk records"),
+        }
+
+        // Version 2 records have no metadata nor track
+        let mut record = MAGIC.to_vec();
+        record.push(2);
+        record.extend(9u64.to_le_bytes());
+        record.push(SplitReason::TimeGap as u8);
+        record.extend(0u32.to_le_bytes());
+        record.extend(1u64.to_le_bytes());
+        record.extend(packet(48, 3.0).as_buf());
+        match read_back(&record).unwrap() {
+            FileContent::Chunks(chunks) => {
+                let (chunk_id, chunk) = &chunks[0];
+                assert_eq!(*chunk_id, 9);
+                assert_eq!(chunk.split_reason, SplitReason::TimeGap);
+                assert_eq!(chunk.packets[0].timestamp_ms, 48);
+                assert!(chunk.metadata == Metadata::default());
+                assert_eq!(chunk.track, None);
+            }
+            FileContent::Packets(_) => panic!("Read as a packet stream"),
+        }
+    }
+
+    #[test]
+    fn corrupt_counts() {
+        let mut header = MAGIC.to_vec();
+        header.push(VERSION);
+        header.extend(0u64.to_le_bytes());
+        header.push(0);
+
+        // A huge rewind count fails at the end of the file instead of reserving it
+        let mut record = header.clone();
+        record.extend(u32::MAX.to_le_bytes());
+        assert!(read_back(&record).is_err());
+
+        // The same for a huge packet count, after empty metadata and no track
+        let mut record = header;
+        record.extend(0u32.to_le_bytes());
+        record.extend([0u8; 4 * 4 + 4]);
+        record.extend(0u32.to_le_bytes());
+        record.extend(u64::MAX.to_le_bytes());
+        record.extend(u64::MAX.to_le_bytes());
+        assert!(read_back(&record).is_err());
+    }
+}
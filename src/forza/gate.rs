@@ -0,0 +1,114 @@
+use super::*;
+
+// A line across the track, between two points of the map
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Gate(pub (f32, f32), pub (f32, f32));
+
+#[derive(Debug, Clone, Copy)]
+pub struct Crossing {
+    pub time_ms: f64,  // Interpolated between the packets around the gate
+    pub fraction: f32, // Where the gate is, between the two packets
+    pub forward: bool,
+}
+
+impl Gate {
+    pub fn center(&self) -> (f32, f32) {
+        ((self.0 .0 + self.1 .0) / 2.0, (self.0 .1 + self.1 .1) / 2.0)
+    }
+
+    // Where the move from `last` to `current` crosses the gate, if it does
+    pub fn crossing(&self, last: &Packet, current: &Packet) -> Option<Crossing> {
+        let cross = |a: (f32, f32), b: (f32, f32)| a.0 * b.1 - a.1 * b.0;
+        let (a, b) = (last.position(), current.position());
+
+        let moved = (b.0 - a.0, b.1 - a.1);
+        let gate = (self.1 .0 - self.0 .0, self.1 .1 - self.0 .1);
+        let denom = cross(moved, gate);
+        if denom == 0.0 {
+            return None;
+        }
+
+        let offset = (self.0 .0 - a.0, self.0 .1 - a.1);
+        let t = cross(offset, gate) / denom; // Along the move
+        let u = cross(offset, moved) / denom; // Along the gate
+        if !(0.0..1.0).contains(&t) || !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let duration = current.timestamp_ms.saturating_sub(last.timestamp_ms) as f64;
+        Some(Crossing {
+            time_ms: last.timestamp_ms as f64 + t as f64 * duration,
+            fraction: t,
+            forward: denom > 0.0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GATE: Gate = Gate((0.0, -10.0), (0.0, 10.0));
+
+    fn packet(timestamp_ms: u32, x: f32, z: f32) -> Packet {
+        Packet {
+            timestamp_ms,
+            position: Vec3 {
+                x,
+                z,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn crossing() {
+        let crossing = GATE
+            .crossing(&packet(1000, -1.0, 0.0), &packet(1100, 3.0, 0.0))
+            .unwrap();
+        assert_eq!(crossing.fraction, 0.25);
+        assert_eq!(crossing.time_ms, 1025.0);
+        assert!(crossing.forward);
+
+        let crossing = GATE
+            .crossing(&packet(1000, 3.0, 5.0), &packet(1100, -1.0, 5.0))
+            .unwrap();
+        assert!(!crossing.forward);
+    }
+
+    #[test]
+    fn no_crossing() {
+        let crossing = |a: (f32, f32), b: (f32, f32)| {
+            GATE.crossing(&packet(1000, a.0, a.1), &packet(1100, b.0, b.1))
+        };
+        assert!(crossing((-1.0, 20.0), (1.0, 20.0)).is_none()); // Around the gate
+        assert!(crossing((-3.0, 0.0), (-1.0, 0.0)).is_none()); // Short of it
+        assert!(crossing((0.0, -5.0), (0.0, 5.0)).is_none()); // Along it
+
+        // A packet right on the gate counts once, for the move leaving it
+        assert!(crossing((-1.0, 0.0), (0.0, 0.0)).is_none());
+        assert_eq!(crossing((0.0, 0.0), (1.0, 0.0)).unwrap().fraction, 0.0);
+    }
+
+    #[test]
+    fn clock_going_back() {
+        let crossing = GATE
+            .crossing(&packet(1100, -1.0, 0.0), &packet(1000, 3.0, 0.0))
+            .unwrap();
+        assert_eq!(crossing.time_ms, 1100.0);
+    }
+
+    #[test]
+    fn crossing_direction() {
+        // Only crossing the way the first crossing went starts a lap
+        let mut chunk = Chunk::new(SplitReason::Start);
+        chunk.set_gate(Some(GATE));
+        for (i, x) in [-5.0, 5.0, -5.0, 5.0, -5.0].into_iter().enumerate() {
+            chunk.push(packet(1000 + i as u32 * 100, x, 0.0));
+        }
+        let laps: Vec<_> = chunk.lap_index.iter().map(|lap| (lap.0, lap.1)).collect();
+        assert_eq!(laps, [(0, 0), (1, 1), (2, 3)]);
+        assert_eq!(chunk.virtual_lap_time(1), Some(0.2));
+    }
+}
@@ -0,0 +1,165 @@
+use super::*;
+
+const MAX_EDITS: usize = 50; // Each edit may keep whole chunks around
+
+pub enum Snapshot {
+    // The state of some chunks, `None` when the chunk did not exist
+    Chunks {
+        order: Vec<ChunkId>,
+        chunks: Vec<(ChunkId, Option<Chunk>)>,
+    },
+    // Edits leaving the packets alone only keep what they change
+    Details(ChunkId, Details),
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Details {
+    pub metadata: Metadata,
+    pub lap_metadata: std::collections::BTreeMap<u16, Metadata>,
+    pub track: Option<TrackId>,
+    pub identified: bool,
+}
+
+pub struct Edit {
+    pub name: String,
+    pub snapshot: Snapshot,
+}
+
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+}
+
+impl History {
+    pub fn record(&mut self, edit: Edit) {
+        if self.undo.len() == MAX_EDITS {
+            self.undo.remove(0);
+        }
+        self.undo.push(edit);
+        self.redo.clear();
+    }
+
+    pub fn pop_undo(&mut self) -> Option<Edit> {
+        self.undo.pop()
+    }
+
+    pub fn push_undo(&mut self, edit: Edit) {
+        self.undo.push(edit);
+    }
+
+    pub fn pop_redo(&mut self) -> Option<Edit> {
+        self.redo.pop()
+    }
+
+    pub fn push_redo(&mut self, edit: Edit) {
+        self.redo.push(edit);
+    }
+
+    pub fn undo_name(&self) -> Option<&str> {
+        self.undo.last().map(|e| e.name.as_str())
+    }
+
+    pub fn redo_name(&self) -> Option<&str> {
+        self.redo.last().map(|e| e.name.as_str())
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(length: u32) -> Chunk {
+        let mut chunk = Chunk::new(SplitReason::Start);
+        for i in 0..length {
+            chunk.push(Packet {
+                timestamp_ms: 1000 + i * 100,
+                ..Default::default()
+            });
+        }
+        chunk
+    }
+
+    fn ids(chunks: &Chunks) -> Vec<ChunkId> {
+        chunks.iter().map(|(chunk_id, _)| chunk_id).collect()
+    }
+
+    #[test]
+    fn undo_split_and_merge() {
+        let mut chunks = Chunks::default();
+        let [a, b, c] = [10, 20, 30].map(|length| chunks.insert(chunk(length)));
+
+        let tail = chunks.split_chunk(b, 5).unwrap();
+        assert_eq!(ids(&chunks), [a, b, tail, c]);
+        chunks.undo();
+        assert_eq!(ids(&chunks), [a, b, c]);
+        assert_eq!(chunks.get(b).unwrap().packets.len(), 20);
+        assert!(chunks.get(tail).is_none());
+        assert_eq!(chunks.redo_name(), Some("Split chunk"));
+
+        // Redoing brings back the same chunk, not a new one
+        chunks.redo();
+        assert_eq!(ids(&chunks), [a, b, tail, c]);
+        assert_eq!(chunks.get(tail).unwrap().packets.len(), 15);
+
+        chunks.merge_chunks(b);
+        assert_eq!(ids(&chunks), [a, b, c]);
+        assert_eq!(chunks.redo_name(), None);
+        chunks.undo();
+        assert_eq!(ids(&chunks), [a, b, tail, c]);
+        assert_eq!(chunks.get(b).unwrap().packets.len(), 5);
+        chunks.undo();
+        assert_eq!(ids(&chunks), [a, b, c]);
+        assert_eq!(chunks.undo_name(), None);
+    }
+
+    #[test]
+    fn undo_remove() {
+        let mut chunks = Chunks::default();
+        let [a, b, c] = [10, 20, 30].map(|length| chunks.insert(chunk(length)));
+
+        chunks.remove_chunk(&ChunkSelector(b, None));
+        assert_eq!(ids(&chunks), [a, c]);
+
+        // Chunks recorded since stay after the restored ones
+        let d = chunks.insert(chunk(5));
+        chunks.undo();
+        assert_eq!(ids(&chunks), [a, b, c, d]);
+        chunks.redo();
+        assert_eq!(ids(&chunks), [a, c, d]);
+    }
+
+    #[test]
+    fn remove_missing() {
+        let mut chunks = Chunks::default();
+        let chunk_id = chunks.insert(chunk(10));
+        chunks.remove_chunk(&ChunkSelector(chunk_id + 1, None));
+        chunks.remove_chunk(&ChunkSelector(chunk_id, Some(3)));
+        assert_eq!(chunks.undo_name(), None);
+        assert_eq!(chunks.len(), 1);
+    }
+
+    #[test]
+    fn history_depth() {
+        let mut history = History::default();
+        let details = chunk(0).details();
+        for chunk_id in 0..MAX_EDITS + 10 {
+            history.record(Edit {
+                name: chunk_id.to_string(),
+                snapshot: Snapshot::Details(chunk_id, details.clone()),
+            });
+        }
+
+        let mut names = vec![];
+        while let Some(edit) = history.pop_undo() {
+            names.push(edit.name);
+        }
+        assert_eq!(names.len(), MAX_EDITS);
+        assert_eq!(names.last().unwrap(), "10");
+    }
+}
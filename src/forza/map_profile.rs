@@ -0,0 +1,399 @@
+use std::io::{Read, Write};
+
+use super::*;
+
+const MAGIC: &[u8; 3] = b"FTP";
+const VERSION: u8 = 2;
+
+const MIN_COVERAGE: f32 = 0.5; // Part of the packets a map must contain to be picked
+const MIN_SPACING: f32 = 10.0; // Between two landmarks, in pixels and in meters
+const MAX_FIT_ERROR: f32 = 0.05; // Of the distance between the landmarks
+
+// The worlds of the games overlap around the origin, so a map belongs to one game
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Game {
+    Fh5,
+    Fh4,
+    Other, // Motorsport circuits, fan-made maps
+}
+
+impl Game {
+    pub const ALL: [Game; 3] = [Game::Fh5, Game::Fh4, Game::Other];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Game::Fh5 => "Forza Horizon 5",
+            Game::Fh4 => "Forza Horizon 4",
+            Game::Other => "Other",
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Game::Fh4,
+            2 => Game::Other,
+            _ => Game::Fh5,
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Game::Fh5 => 0,
+            Game::Fh4 => 1,
+            Game::Other => 2,
+        }
+    }
+}
+
+// A landmark in pixels from the centre of the image, and its world position
+pub type CalibrationPoint = ((f32, f32), (f32, f32));
+
+// A background image of the map, and where it lies in the world
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapProfile {
+    pub name: String,
+    pub game: Game,
+    pub image: Option<String>, // Path of the image, None for the embedded map of Mexico
+    pub center: (f32, f32),    // World position of the centre of the image
+    pub scale: f32,            // Meters per pixel
+    pub size: (f32, f32),      // Pixels, known once the image is loaded
+}
+
+impl MapProfile {
+    pub fn new(name: String, game: Game, image: String) -> Self {
+        Self {
+            name,
+            game,
+            image: Some(image),
+            center: (0.0, 0.0),
+            scale: 1.0,
+            size: (0.0, 0.0),
+        }
+    }
+
+    pub fn mexico() -> Self {
+        Self {
+            name: "Mexico (FH5)".to_owned(),
+            game: Game::Fh5,
+            image: None,
+            center: (-1755.0, 922.0),
+            scale: 3.475,
+            size: (5615.0, 3245.0),
+        }
+    }
+
+    pub fn contains(&self, (x, z): (f32, f32)) -> bool {
+        let half_width = self.size.0 * self.scale / 2.0;
+        let half_height = self.size.1 * self.scale / 2.0;
+        (x - self.center.0).abs() <= half_width && (z - self.center.1).abs() <= half_height
+    }
+
+    // Needs two distinct landmarks, a third one averages the errors. Returns the
+    // mean distance in meters between the landmarks and where the image puts them
+    pub fn calibrate(&mut self, points: &[CalibrationPoint]) -> Result<f32, String> {
+        let distance = |a: (f32, f32), b: (f32, f32)| (a.0 - b.0).hypot(a.1 - b.1);
+
+        let pairs: Vec<_> = points
+            .iter()
+            .enumerate()
+            .flat_map(|(i, a)| points[i + 1..].iter().map(move |b| (a, b)))
+            .collect();
+        if pairs.is_empty() {
+            return Err("Place at least two landmarks".to_owned());
+        }
+        let too_close = pairs
+            .iter()
+            .any(|((image_a, world_a), (image_b, world_b))| {
+                distance(*image_a, *image_b) < MIN_SPACING
+                    || distance(*world_a, *world_b) < MIN_SPACING
+            });
+        if too_close {
+            return Err("Two landmarks are at the same place, pick them further apart".to_owned());
+        }
+
+        let image_distance: f32 = pairs.iter().map(|((a, _), (b, _))| distance(*a, *b)).sum();
+        let world_distance: f32 = pairs.iter().map(|((_, a), (_, b))| distance(*a, *b)).sum();
+        let scale = world_distance / image_distance;
+        let count = points.len() as f32;
+        let center = points.iter().fold((0.0, 0.0), |sum, (image, world)| {
+            (
+                sum.0 + (world.0 - scale * image.0) / count,
+                sum.1 + (world.1 - scale * image.1) / count,
+            )
+        });
+
+        // The image is only moved and scaled, a rotated one or a missed click do not fit
+        let fit_error = points
+            .iter()
+            .map(|(image, world)| {
+                let placed = (center.0 + scale * image.0, center.1 + scale * image.1);
+                distance(placed, *world)
+            })
+            .sum::<f32>()
+            / count;
+        if fit_error > MAX_FIT_ERROR * world_distance / pairs.len() as f32 {
+            return Err(format!(
+                "The landmarks are {:.0} m away from where the image puts them. \
+                 Check the clicks match, and that the image is north up",
+                fit_error
+            ));
+        }
+
+        self.scale = scale;
+        self.center = center;
+        Ok(fit_error)
+    }
+}
+
+pub struct MapProfiles {
+    profiles: Vec<MapProfile>,
+    game: Game, // Game being played, whose maps are picked
+}
+
+impl Default for MapProfiles {
+    fn default() -> Self {
+        Self {
+            profiles: vec![MapProfile::mexico()],
+            game: Game::Fh5,
+        }
+    }
+}
+
+impl MapProfiles {
+    pub fn game(&self) -> Game {
+        self.game
+    }
+
+    pub fn set_game(&mut self, game: Game) {
+        self.game = game;
+    }
+
+    pub fn get(&self, index: usize) -> Option<&MapProfile> {
+        self.profiles.get(index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut MapProfile> {
+        self.profiles.get_mut(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &MapProfile> {
+        self.profiles.iter()
+    }
+
+    pub fn insert(&mut self, profile: MapProfile) -> usize {
+        self.profiles.push(profile);
+        self.profiles.len() - 1
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.profiles.len() {
+            self.profiles.remove(index);
+        }
+        if self.profiles.is_empty() {
+            self.profiles.push(MapProfile::mexico());
+        }
+    }
+
+    // The map of the game where the packets were recorded. The smallest map
+    // wins, so a detailed map of a region beats the whole country
+    pub fn pick(&self, packets: &[Packet]) -> Option<usize> {
+        let area = |profile: &MapProfile| profile.size.0 * profile.size.1 * profile.scale.powi(2);
+        self.covering(packets)
+            .filter(|(_, profile)| profile.game == self.game)
+            .min_by(|(_, a), (_, b)| area(a).total_cmp(&area(b)))
+            .map(|(index, _)| index)
+    }
+
+    // FH4 and FH5 send the same packets, only the maps covering them can tell
+    // the games apart, when they all belong to the same one
+    pub fn detect_game(&self, packets: &[Packet]) -> Option<Game> {
+        let mut games = self.covering(packets).map(|(_, profile)| profile.game);
+        let game = games.next()?;
+        games.all(|other| other == game).then_some(game)
+    }
+
+    fn covering<'a>(
+        &'a self,
+        packets: &[Packet],
+    ) -> impl Iterator<Item = (usize, &'a MapProfile)> + 'a {
+        let step_by = 1 + packets.len() / 500;
+        let positions: Vec<_> = packets
+            .iter()
+            .step_by(step_by)
+            .map(|p| p.position())
+            .collect();
+
+        self.profiles
+            .iter()
+            .enumerate()
+            .filter(move |(_, profile)| {
+                let inside = positions.iter().filter(|p| profile.contains(**p)).count();
+                !positions.is_empty() && inside as f32 >= positions.len() as f32 * MIN_COVERAGE
+            })
+    }
+
+    // Without a file yet, only the embedded map is known
+    pub fn load() -> std::io::Result<Self> {
+        match config_path("maps.ftp") {
+            Some(path) if path.exists() => Self::read(&mut std::fs::File::open(path)?),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = config_path("maps.ftp").ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "No home directory")
+        })?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let mut output = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.write(&mut output)?;
+        output.flush()
+    }
+
+    fn write(&self, output: &mut impl Write) -> std::io::Result<()> {
+        output.write_all(MAGIC)?;
+        output.write_all(&[VERSION])?;
+        output.write_all(&[self.game.to_u8()])?;
+
+        output.write_all(&(self.profiles.len() as u32).to_le_bytes())?;
+        for profile in &self.profiles {
+            write_string(output, &profile.name)?;
+            output.write_all(&[profile.game.to_u8()])?;
+            output.write_all(&[profile.image.is_some() as u8])?;
+            if let Some(image) = &profile.image {
+                write_string(output, image)?;
+            }
+
+            let (x, z) = profile.center;
+            let (width, height) = profile.size;
+            for value in [x, z, profile.scale, width, height] {
+                output.write_all(&value.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn read(input: &mut impl Read) -> std::io::Result<Self> {
+        let mut header = [0u8; 4];
+        input.read_exact(&mut header)?;
+        if &header[..3] != MAGIC || !(1..=VERSION).contains(&header[3]) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Not a map library",
+            ));
+        }
+
+        // Version 1 only knew the maps of FH5
+        let version = header[3];
+        let game = match version {
+            1 => Game::Fh5,
+            _ => Game::from_u8(read_u8(input)?),
+        };
+
+        let mut profiles = Vec::new();
+        for _ in 0..read_u32(input)? {
+            let name = read_string(input)?;
+            let profile_game = match version {
+                1 => Game::Fh5,
+                _ => Game::from_u8(read_u8(input)?),
+            };
+            let image = match read_u8(input)? {
+                0 => None,
+                _ => Some(read_string(input)?),
+            };
+            profiles.push(MapProfile {
+                name,
+                game: profile_game,
+                image,
+                center: (read_f32(input)?, read_f32(input)?),
+                scale: read_f32(input)?,
+                size: (read_f32(input)?, read_f32(input)?),
+            });
+        }
+
+        // The embedded map is always available
+        if profiles.iter().all(|profile| profile.image.is_some()) {
+            profiles.insert(0, MapProfile::mexico());
+        }
+        Ok(Self { profiles, game })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile() -> MapProfile {
+        MapProfile::new("Test".to_owned(), Game::Other, "test.png".to_owned())
+    }
+
+    fn map(game: Game, center: (f32, f32)) -> MapProfile {
+        MapProfile {
+            center,
+            size: (1000.0, 1000.0),
+            ..MapProfile::new("Test".to_owned(), game, "test.png".to_owned())
+        }
+    }
+
+    fn packets(x: f32) -> Vec<Packet> {
+        (0..10)
+            .map(|i| Packet {
+                position: Vec3 {
+                    x: x + i as f32,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn detect_game() {
+        let maps = MapProfiles {
+            profiles: vec![map(Game::Fh5, (0.0, 0.0)), map(Game::Fh4, (800.0, 0.0))],
+            game: Game::Fh5,
+        };
+        assert_eq!(maps.detect_game(&packets(-400.0)), Some(Game::Fh5));
+        assert_eq!(maps.detect_game(&packets(1200.0)), Some(Game::Fh4));
+        assert_eq!(maps.pick(&packets(1200.0)), None);
+
+        // Where the maps of both games overlap, the game stays the one picked
+        assert_eq!(maps.detect_game(&packets(400.0)), None);
+        assert_eq!(maps.detect_game(&packets(5000.0)), None);
+        assert_eq!(maps.detect_game(&[]), None);
+    }
+
+    #[test]
+    fn calibrate() {
+        // Two meters a pixel, centred on (100, 50)
+        let place = |x: f32, y: f32| ((x, y), (100.0 + 2.0 * x, 50.0 + 2.0 * y));
+        let mut profile = profile();
+        let fit_error = profile
+            .calibrate(&[place(-200.0, 0.0), place(300.0, 100.0), place(0.0, -150.0)])
+            .unwrap();
+        assert!(fit_error < 0.01, "{}", fit_error);
+        assert!((profile.scale - 2.0).abs() < 0.001);
+        assert!((profile.center.0 - 100.0).abs() < 0.01);
+        assert!((profile.center.1 - 50.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn bad_landmarks() {
+        let mut map = profile();
+        let place = |x: f32, y: f32| ((x, y), (x, y));
+        assert!(map.calibrate(&[place(10.0, 20.0)]).is_err());
+        assert!(map
+            .calibrate(&[place(10.0, 20.0), place(12.0, 20.0)])
+            .is_err());
+
+        // A quarter turn keeps the distances, but not the directions
+        let turned = |x: f32, y: f32| ((x, y), (-y, x));
+        assert!(map
+            .calibrate(&[turned(-200.0, 0.0), turned(300.0, 100.0)])
+            .is_err());
+        assert_eq!(map, profile());
+    }
+}
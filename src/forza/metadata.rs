@@ -0,0 +1,83 @@
+#[derive(Clone, Default, PartialEq)]
+pub struct Metadata {
+    pub name: String,
+    pub notes: String,
+    pub tags: Vec<String>,
+    pub driver: String,
+    pub setup: String,
+}
+
+impl Metadata {
+    pub fn is_empty(&self) -> bool {
+        *self == Metadata::default()
+    }
+
+    // Fill the empty fields from another one, and keep the notes and tags of both
+    pub fn merge(&mut self, other: Metadata) {
+        for (field, other) in [
+            (&mut self.name, other.name),
+            (&mut self.driver, other.driver),
+            (&mut self.setup, other.setup),
+        ] {
+            if field.is_empty() {
+                *field = other;
+            }
+        }
+
+        if self.notes.is_empty() {
+            self.notes = other.notes;
+        } else if !other.notes.is_empty() && other.notes != self.notes {
+            self.notes = format!("{}\n{}", self.notes, other.notes);
+        }
+
+        for tag in other.tags {
+            if !self.tags.contains(&tag) {
+                self.tags.push(tag);
+            }
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        Some(self.name.as_str()).filter(|name| !name.is_empty())
+    }
+
+    // Every word of the filter must appear in one of the fields
+    pub fn matches(&self, filter: &str) -> bool {
+        let fields = [&self.name, &self.notes, &self.driver, &self.setup]
+            .into_iter()
+            .chain(self.tags.iter())
+            .map(|field| field.to_lowercase())
+            .collect::<Vec<_>>();
+
+        filter.to_lowercase().split_whitespace().all(|word| {
+            let word = word.trim_start_matches('#');
+            fields.iter().any(|field| field.contains(word))
+        })
+    }
+
+    pub fn summary(&self) -> String {
+        let mut lines = Vec::new();
+        if !self.driver.is_empty() {
+            lines.push(format!("Driver: {}", self.driver));
+        }
+        if !self.setup.is_empty() {
+            lines.push(format!("Setup: {}", self.setup));
+        }
+        if !self.tags.is_empty() {
+            let tags: Vec<_> = self.tags.iter().map(|t| format!("#{}", t)).collect();
+            lines.push(tags.join(" "));
+        }
+        if !self.notes.is_empty() {
+            lines.push(self.notes.clone());
+        }
+        lines.join("\n")
+    }
+
+    pub fn parse_tags(text: &str) -> Vec<String> {
+        text.split(|c: char| c == ',' || c.is_whitespace())
+            .map(|tag| tag.trim_start_matches('#'))
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| tag.to_owned())
+            .collect()
+    }
+}
@@ -0,0 +1,140 @@
+use super::*;
+
+const SPACING: f32 = 5.0; // meters between two stations of the centerline
+const SEARCH_WINDOW: usize = 40; // Stations ahead where the next packet is looked for
+const MAX_OFFSET: f32 = 40.0; // Farther from the centerline, the car is off the track
+const CAR_WIDTH: f32 = 2.0;
+const MIN_WIDTH: f32 = 10.0; // A single line still gets a road around it
+const EDGE_PERCENTILES: (f32, f32) = (0.05, 0.95);
+const SMOOTHING: usize = 3; // Stations averaged on each side
+
+// A track drawn from the laps driven on it, for the places without a map
+#[derive(Debug, Default, Clone)]
+pub struct Outline {
+    pub center: Vec<(f32, f32)>,
+    pub left: Vec<(f32, f32)>,
+    pub right: Vec<(f32, f32)>,
+    pub closed: bool, // The last station joins the first one
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+fn smooth(values: &[f32], closed: bool) -> Vec<f32> {
+    let len = values.len() as isize;
+    (0..len)
+        .map(|i| {
+            let window =
+                (i - SMOOTHING as isize..=i + SMOOTHING as isize).filter_map(|j| match closed {
+                    true => Some(values[j.rem_euclid(len) as usize]),
+                    false => (0..len).contains(&j).then(|| values[j as usize]),
+                });
+            let (sum, count) = window.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+            sum / count as f32
+        })
+        .collect()
+}
+
+impl Outline {
+    // The first lap gives the stations, the others how wide the track is around them
+    pub fn new(laps: &[&[Packet]]) -> Option<Self> {
+        let mut stations: Vec<(f32, f32)> = Vec::new();
+        for p in laps.first()?.iter() {
+            let position = p.position();
+            if stations
+                .last()
+                .is_none_or(|last| distance(*last, position) >= SPACING)
+            {
+                stations.push(position);
+            }
+        }
+        if stations.len() < 3 {
+            return None;
+        }
+        let len = stations.len();
+        let closed = distance(stations[0], stations[len - 1]) < SPACING * 3.0;
+
+        // Normals point to the left of the driving direction
+        let normals: Vec<_> = (0..len)
+            .map(|i| {
+                let (before, after) = match closed {
+                    true => (stations[(i + len - 1) % len], stations[(i + 1) % len]),
+                    false => (
+                        stations[i.saturating_sub(1)],
+                        stations[(i + 1).min(len - 1)],
+                    ),
+                };
+                let (dx, dz) = (after.0 - before.0, after.1 - before.1);
+                let norm = dx.hypot(dz).max(f32::EPSILON);
+                (-dz / norm, dx / norm)
+            })
+            .collect();
+
+        let nearest = |position: (f32, f32), range: &mut dyn Iterator<Item = usize>| {
+            range
+                .map(|i| (i, distance(stations[i], position)))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(i, _)| i)
+        };
+
+        // Follow each lap along the stations, to keep the offsets of each part of the track
+        let mut offsets = vec![Vec::new(); len];
+        for lap in laps {
+            let mut station = match lap.first() {
+                Some(first) => nearest(first.position(), &mut (0..len)).unwrap_or_default(),
+                None => continue,
+            };
+            for p in lap.iter() {
+                let mut window = (0..SEARCH_WINDOW).filter_map(|i| match closed {
+                    true => Some((station + i) % len),
+                    false => Some(station + i).filter(|s| *s < len),
+                });
+                station = nearest(p.position(), &mut window).unwrap_or(station);
+
+                let (x, z) = p.position();
+                let (origin, normal) = (stations[station], normals[station]);
+                let offset = (x - origin.0) * normal.0 + (z - origin.1) * normal.1;
+                if offset.abs() <= MAX_OFFSET {
+                    offsets[station].push(offset);
+                }
+            }
+        }
+
+        let (mut lows, mut highs) = (Vec::with_capacity(len), Vec::with_capacity(len));
+        for offsets in offsets {
+            let distribution = Distribution::new(offsets);
+            let low = distribution.percentile(EDGE_PERCENTILES.0) - CAR_WIDTH / 2.0;
+            let high = distribution.percentile(EDGE_PERCENTILES.1) + CAR_WIDTH / 2.0;
+            let missing = (MIN_WIDTH - (high - low)).max(0.0) / 2.0;
+            lows.push(low - missing);
+            highs.push(high + missing);
+        }
+        let (lows, highs) = (smooth(&lows, closed), smooth(&highs, closed));
+
+        let at = |i: usize, offset: f32| {
+            let (origin, normal) = (stations[i], normals[i]);
+            (origin.0 + normal.0 * offset, origin.1 + normal.1 * offset)
+        };
+        Some(Self {
+            center: (0..len)
+                .map(|i| at(i, (lows[i] + highs[i]) / 2.0))
+                .collect(),
+            left: (0..len).map(|i| at(i, highs[i])).collect(),
+            right: (0..len).map(|i| at(i, lows[i])).collect(),
+            closed,
+        })
+    }
+
+    // From the left edge to the right edge, at the first station
+    pub fn start_line(&self) -> Option<Gate> {
+        Some(Gate(*self.left.first()?, *self.right.first()?))
+    }
+
+    // A few stations along the centerline, in the driving direction
+    pub fn direction(&self) -> Option<((f32, f32), (f32, f32))> {
+        let start = *self.center.first()?;
+        let ahead = *self.center.get(4).or(self.center.last())?;
+        Some((start, ahead))
+    }
+}
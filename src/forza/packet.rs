@@ -21,7 +21,7 @@ pub type PacketRaw = [u8; std::mem::size_of::<Packet>()];
 pub type PacketVec = std::vec::Vec<Packet>;
 
 #[repr(C)]
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Packet {
     pub is_race_on: i32,   // = 1 when race is on. = 0 when in menus/race stopped
     pub timestamp_ms: u32, //Can overflow to 0 eventually
@@ -71,7 +71,7 @@ pub struct Packet {
     pub normalized_aibrake_difference: i8,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum GameMode {
     FreeRoam,
     Race,
@@ -1,13 +1,17 @@
 use std::{
     net::UdpSocket,
     sync::mpsc::{Iter, Receiver, TryIter},
+    sync::{Arc, Mutex},
 };
 
 use super::*;
 
+type Wake = Arc<Mutex<Option<Box<dyn Fn() + Send>>>>;
+
 pub struct Socket {
     _thread: std::thread::JoinHandle<()>,
     receiver: Receiver<Packet>,
+    wake: Wake, // Called for every packet, to refresh the interface
 }
 
 impl Default for Socket {
@@ -19,10 +23,12 @@ impl Default for Socket {
 impl Socket {
     pub fn new(addr: &str) -> Self {
         let (sender, receiver) = std::sync::mpsc::channel();
+        let wake: Wake = Arc::default();
 
         let socket = UdpSocket::bind(addr).expect("couldn't bind to address");
         println!("Listening on {:?}...", socket.local_addr().unwrap());
 
+        let thread_wake = wake.clone();
         let thread = std::thread::spawn(move || {
             let mut last_packet_timestamp = 0u32;
             loop {
@@ -38,15 +44,23 @@ impl Socket {
 
                 last_packet_timestamp = packet.timestamp_ms;
                 sender.send(packet).ok();
+                if let Some(wake) = &*thread_wake.lock().unwrap() {
+                    wake();
+                }
             }
         });
 
         Self {
             _thread: thread,
             receiver,
+            wake,
         }
     }
 
+    pub fn set_wake(&self, wake: Option<Box<dyn Fn() + Send>>) {
+        *self.wake.lock().unwrap() = wake;
+    }
+
     pub fn iter(&self) -> Iter<'_, Packet> {
         self.receiver.iter()
     }
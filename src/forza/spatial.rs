@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+const POINTS_PER_CELL: f32 = 8.0; // On average, over the bounding box of the points
+const CELL_SIZE_RANGE: (f32, f32) = (1.0, 1000.0);
+
+type Cell = (i32, i32);
+
+// A grid of the positions of packets, to find them around a place without
+// going through all of them
+#[derive(Default)]
+pub struct SpatialIndex {
+    cell_size: f32,
+    cells: HashMap<Cell, Vec<usize>>,
+    positions: Vec<(f32, f32)>,
+    built: usize, // Positions the cell size was chosen for
+}
+
+impl SpatialIndex {
+    pub fn new(positions: Vec<(f32, f32)>) -> Self {
+        let (min, max) = positions.iter().fold(
+            (
+                (f32::INFINITY, f32::INFINITY),
+                (f32::NEG_INFINITY, f32::NEG_INFINITY),
+            ),
+            |(min, max), (x, z)| {
+                (
+                    (min.0.min(*x), min.1.min(*z)),
+                    (max.0.max(*x), max.1.max(*z)),
+                )
+            },
+        );
+        let area = ((max.0 - min.0) * (max.1 - min.1)).max(0.0);
+        let cell_size = (area * POINTS_PER_CELL / positions.len().max(1) as f32)
+            .sqrt()
+            .clamp(CELL_SIZE_RANGE.0, CELL_SIZE_RANGE.1);
+
+        let mut index = Self {
+            cell_size,
+            cells: HashMap::new(),
+            positions: Vec::new(),
+            built: positions.len(),
+        };
+        for (i, position) in positions.iter().enumerate() {
+            let cell = index.cell_of(*position);
+            index.cells.entry(cell).or_default().push(i);
+        }
+        index.positions = positions;
+        index
+    }
+
+    // The cells are sized again once the positions doubled
+    pub fn extend(&mut self, positions: impl IntoIterator<Item = (f32, f32)>) {
+        for position in positions {
+            let cell = self.cell_of(position);
+            self.cells
+                .entry(cell)
+                .or_default()
+                .push(self.positions.len());
+            self.positions.push(position);
+        }
+
+        if self.built == 0 || self.positions.len() > self.built * 2 + 256 {
+            *self = Self::new(std::mem::take(&mut self.positions));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    fn cell_of(&self, (x, z): (f32, f32)) -> Cell {
+        (
+            (x / self.cell_size).floor() as i32,
+            (z / self.cell_size).floor() as i32,
+        )
+    }
+
+    // The indices in the cells overlapping a rectangle
+    fn candidates(&self, min: (f32, f32), max: (f32, f32)) -> Vec<usize> {
+        let (first, last) = (self.cell_of(min), self.cell_of(max));
+        let cell_count = (last.0 - first.0 + 1) as i64 * (last.1 - first.1 + 1) as i64;
+
+        // Zoomed out, going through the occupied cells is faster
+        let mut candidates: Vec<usize> = match cell_count > self.cells.len() as i64 {
+            true => self
+                .cells
+                .iter()
+                .filter(|((x, z), _)| {
+                    (first.0..=last.0).contains(x) && (first.1..=last.1).contains(z)
+                })
+                .flat_map(|(_, indices)| indices.iter().cloned())
+                .collect(),
+            false => (first.0..=last.0)
+                .flat_map(|x| (first.1..=last.1).map(move |z| (x, z)))
+                .filter_map(|cell| self.cells.get(&cell))
+                .flatten()
+                .cloned()
+                .collect(),
+        };
+        candidates.sort_unstable();
+        candidates
+    }
+
+    // The closest position within the radius, and its distance
+    pub fn nearest(&self, (x, z): (f32, f32), radius: f32) -> Option<(usize, f32)> {
+        self.candidates((x - radius, z - radius), (x + radius, z + radius))
+            .into_iter()
+            .map(|i| {
+                let position = self.positions[i];
+                (i, (position.0 - x).hypot(position.1 - z))
+            })
+            .filter(|(_, distance)| *distance <= radius)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    // Sorted indices of the positions in the rectangle
+    pub fn in_rect(&self, min: (f32, f32), max: (f32, f32)) -> Vec<usize> {
+        let (min, max) = (
+            (min.0.min(max.0), min.1.min(max.1)),
+            (min.0.max(max.0), min.1.max(max.1)),
+        );
+        self.candidates(min, max)
+            .into_iter()
+            .filter(|i| {
+                let (x, z) = self.positions[*i];
+                (min.0..=max.0).contains(&x) && (min.1..=max.1).contains(&z)
+            })
+            .collect()
+    }
+
+    // Sorted indices of the positions in a polygon, closed between its last and first points
+    pub fn in_polygon(&self, polygon: &[(f32, f32)]) -> Vec<usize> {
+        if polygon.len() < 3 {
+            return Vec::new();
+        }
+        let (min, max) = polygon.iter().fold(
+            (
+                (f32::INFINITY, f32::INFINITY),
+                (f32::NEG_INFINITY, f32::NEG_INFINITY),
+            ),
+            |(min, max), (x, z)| {
+                (
+                    (min.0.min(*x), min.1.min(*z)),
+                    (max.0.max(*x), max.1.max(*z)),
+                )
+            },
+        );
+
+        // Even-odd rule: a ray to the right crosses the edges an odd number of times
+        let contains = |(x, z): (f32, f32)| {
+            let mut inside = false;
+            let mut previous = polygon[polygon.len() - 1];
+            for point in polygon {
+                if (point.1 > z) != (previous.1 > z)
+                    && x < previous.0
+                        + (z - previous.1) / (point.1 - previous.1) * (point.0 - previous.0)
+                {
+                    inside = !inside;
+                }
+                previous = *point;
+            }
+            inside
+        };
+        self.in_rect(min, max)
+            .into_iter()
+            .filter(|i| contains(self.positions[*i]))
+            .collect()
+    }
+}
@@ -0,0 +1,79 @@
+use super::*;
+
+const GRAVITY: f32 = 9.81;
+const TIME_TOLERANCE: f32 = 0.5; // seconds between the game and the timestamps
+
+#[derive(Clone, Default)]
+pub struct LapSummary {
+    pub lap_num: u16,
+    pub lap_time: Option<f32>, // As reported by the game
+    pub elapsed: f32,          // From the timestamps of the packets
+    pub top_speed: f32,        // meters per second
+    pub min_speed: f32,        // meters per second
+    pub average_speed: f32,    // meters per second
+    pub max_g: f32,            // Horizontal acceleration, in g
+    pub full_throttle: f32,    // seconds
+    pub braking: f32,          // seconds
+    pub fuel_used: f32,        // Fraction of the tank
+    pub gear_changes: usize,
+}
+
+impl LapSummary {
+    pub fn new(lap_num: u16, packets: &[Packet], lap_time: Option<f32>) -> Self {
+        let mut summary = LapSummary {
+            lap_num,
+            lap_time,
+            min_speed: f32::INFINITY,
+            ..Default::default()
+        };
+        let (first, last) = match (packets.first(), packets.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => {
+                return Self {
+                    lap_num,
+                    ..Default::default()
+                }
+            }
+        };
+
+        let mut speed_sum = 0.0;
+        for p in packets {
+            summary.top_speed = summary.top_speed.max(p.speed);
+            summary.min_speed = summary.min_speed.min(p.speed);
+            summary.max_g = summary
+                .max_g
+                .max(p.acceleration.x.hypot(p.acceleration.z) / GRAVITY);
+            speed_sum += p.speed;
+        }
+        summary.average_speed = speed_sum / packets.len() as f32;
+
+        // Each packet lasts until the next one
+        for pair in packets.windows(2) {
+            let duration =
+                pair[1].timestamp_ms.saturating_sub(pair[0].timestamp_ms) as f32 / 1000.0;
+            summary.elapsed += duration;
+            if pair[0].accel == u8::MAX {
+                summary.full_throttle += duration;
+            }
+            if pair[0].brake > 0 {
+                summary.braking += duration;
+            }
+            if pair[0].gear != pair[1].gear {
+                summary.gear_changes += 1;
+            }
+        }
+        summary.fuel_used = first.fuel - last.fuel;
+        summary
+    }
+
+    // The game time when known, the recorded time otherwise
+    pub fn time(&self) -> f32 {
+        self.lap_time.unwrap_or(self.elapsed)
+    }
+
+    // Dropped packets or rewinds make the recorded time disagree with the game
+    pub fn is_consistent(&self) -> bool {
+        self.lap_time
+            .is_none_or(|lap_time| (lap_time - self.elapsed).abs() <= TIME_TOLERANCE)
+    }
+}
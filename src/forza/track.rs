@@ -0,0 +1,380 @@
+use std::io::{Read, Write};
+
+use super::*;
+
+pub type TrackId = usize;
+
+const MAGIC: &[u8; 3] = b"FTT";
+const VERSION: u8 = 2;
+
+const REFERENCE_SPACING: f32 = 10.0; // meters between the points of a reference path
+const START_RADIUS: f32 = 150.0; // The starting grid can be far behind the line
+const MAX_DEVIATION: f32 = 30.0; // Mean distance to the reference path, in meters
+const MATCH_SAMPLES: usize = 200;
+
+#[derive(Clone, Default)]
+pub struct Track {
+    pub name: String,
+    pub start: Option<Gate>,
+    pub sectors: Vec<Gate>, // In driving order, the lap has one more sector
+    pub best_sectors: Vec<Option<f32>>,
+    pub best_lap: Option<f32>,
+    pub reference: Vec<(f32, f32)>, // Path of a lap, used to identify the races
+}
+
+impl Track {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            ..Default::default()
+        }
+    }
+
+    // Keep the path of a lap, resampled so every track has the same density
+    pub fn set_reference(&mut self, packets: &[Packet]) {
+        let mut reference: Vec<(f32, f32)> = Vec::new();
+        for p in packets {
+            let position = p.position();
+            let far_enough = reference
+                .last()
+                .is_none_or(|last| distance(*last, position) >= REFERENCE_SPACING);
+            if far_enough {
+                reference.push(position);
+            }
+        }
+        self.reference = reference;
+    }
+
+    // Mean distance between the driven path and the reference one, if they match.
+    // Measured both ways, so a drive along only a part of the reference does not match
+    pub fn match_score(&self, packets: &[Packet]) -> Option<f32> {
+        let start = *self.reference.first()?;
+        if distance(packets.first()?.position(), start) > START_RADIUS {
+            return None;
+        }
+
+        let step = 1 + packets.len() / MATCH_SAMPLES;
+        let path: Vec<_> = packets.iter().step_by(step).map(|p| p.position()).collect();
+        let step = 1 + self.reference.len() / MATCH_SAMPLES;
+        let reference: Vec<_> = self.reference.iter().step_by(step).copied().collect();
+        let score = mean_distance(&path, &self.reference).max(mean_distance(&reference, &path));
+
+        (score <= MAX_DEVIATION).then_some(score)
+    }
+
+    pub fn sector_count(&self) -> usize {
+        self.sectors.len() + 1
+    }
+
+    // Sum of the best sectors, the lap the driver could do
+    pub fn theoretical_best(&self) -> Option<f32> {
+        if self.sectors.is_empty() {
+            return None;
+        }
+        self.best_sectors
+            .iter()
+            .try_fold(0.0, |sum, sector| sector.map(|s| sum + s))
+    }
+
+    pub fn set_start(&mut self, gate: Option<Gate>) {
+        self.start = gate;
+        self.reset_bests();
+    }
+
+    pub fn add_sector(&mut self, gate: Gate) {
+        self.sectors.push(gate);
+        self.reset_bests();
+    }
+
+    pub fn clear_sectors(&mut self) {
+        self.sectors.clear();
+        self.reset_bests();
+    }
+
+    pub fn reset_bests(&mut self) {
+        self.best_sectors = vec![None; self.sector_count()];
+        self.best_lap = None;
+    }
+
+    // Keep the best of each sector, returns if anything improved
+    pub fn update_bests(&mut self, lap_time: Option<f32>, sector_times: &[Option<f32>]) -> bool {
+        let is_better = |best: Option<f32>, time: f32| best.is_none_or(|best| time < best);
+
+        self.best_sectors.resize(self.sector_count(), None);
+        let mut improved = false;
+        for (best, time) in self.best_sectors.iter_mut().zip(sector_times) {
+            if let Some(time) = *time {
+                if is_better(*best, time) {
+                    *best = Some(time);
+                    improved = true;
+                }
+            }
+        }
+
+        if let Some(lap_time) = lap_time {
+            if is_better(self.best_lap, lap_time) {
+                self.best_lap = Some(lap_time);
+                improved = true;
+            }
+        }
+        improved
+    }
+
+    // Time of each sector, using the lap clock of every packet
+    pub fn sector_times(
+        &self,
+        packets: &[Packet],
+        lap_clock: impl Fn(&Packet) -> f32,
+        lap_time: Option<f32>,
+    ) -> Vec<Option<f32>> {
+        let mut splits = Vec::with_capacity(self.sector_count());
+        let mut gates = self.sectors.iter();
+        let mut next_gate = gates.next();
+
+        for pair in packets.windows(2) {
+            let crossing = next_gate.and_then(|gate| gate.crossing(&pair[0], &pair[1]));
+            if let Some(crossing) = crossing {
+                let (last, current) = (lap_clock(&pair[0]), lap_clock(&pair[1]));
+                splits.push(last + crossing.fraction * (current - last));
+                next_gate = gates.next();
+            }
+        }
+
+        let mut sector_times = vec![None; self.sector_count()];
+        if splits.len() != self.sectors.len() {
+            // Missed a sector line, only the sectors before it are valid
+            let mut last_split = 0.0;
+            for (time, split) in sector_times.iter_mut().zip(&splits) {
+                *time = Some(split - last_split);
+                last_split = *split;
+            }
+            return sector_times;
+        }
+
+        let mut last_split = 0.0;
+        for (time, split) in sector_times
+            .iter_mut()
+            .zip(splits.iter().map(|s| Some(*s)).chain([lap_time]))
+        {
+            *time = split.map(|split| split - last_split);
+            last_split = split.unwrap_or(last_split);
+        }
+        sector_times
+    }
+}
+
+#[derive(Default)]
+pub struct Tracks {
+    tracks: std::collections::BTreeMap<TrackId, Track>,
+    next_id: TrackId,
+    revision: u64, // Bumped on every change of the gates
+}
+
+impl Tracks {
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn insert(&mut self, track: Track) -> TrackId {
+        let track_id = self.next_id;
+        self.next_id += 1;
+        self.tracks.insert(track_id, track);
+        track_id
+    }
+
+    pub fn remove(&mut self, track_id: TrackId) {
+        self.tracks.remove(&track_id);
+        self.revision += 1;
+    }
+
+    pub fn get(&self, track_id: TrackId) -> Option<&Track> {
+        self.tracks.get(&track_id)
+    }
+
+    // Not for the gates, the sector times would not be computed again
+    pub fn get_mut(&mut self, track_id: TrackId) -> Option<&mut Track> {
+        self.tracks.get_mut(&track_id)
+    }
+
+    fn edit_gates(&mut self, track_id: TrackId, edit: impl FnOnce(&mut Track)) {
+        if let Some(track) = self.tracks.get_mut(&track_id) {
+            edit(track);
+            self.revision += 1;
+        }
+    }
+
+    pub fn set_start(&mut self, track_id: TrackId, gate: Option<Gate>) {
+        self.edit_gates(track_id, |track| track.set_start(gate));
+    }
+
+    pub fn add_sector(&mut self, track_id: TrackId, gate: Gate) {
+        self.edit_gates(track_id, |track| track.add_sector(gate));
+    }
+
+    pub fn clear_sectors(&mut self, track_id: TrackId) {
+        self.edit_gates(track_id, Track::clear_sectors);
+    }
+
+    // The track whose reference path the race follows best
+    pub fn identify(&self, packets: &[Packet]) -> Option<TrackId> {
+        self.iter()
+            .filter_map(|(track_id, track)| Some((track_id, track.match_score(packets)?)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(track_id, _)| track_id)
+    }
+
+    pub(super) fn update_bests(
+        &mut self,
+        track_id: TrackId,
+        lap_time: Option<f32>,
+        sector_times: &[Option<f32>],
+    ) -> bool {
+        self.tracks
+            .get_mut(&track_id)
+            .is_some_and(|track| track.update_bests(lap_time, sector_times))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (TrackId, &Track)> {
+        self.tracks
+            .iter()
+            .map(|(track_id, track)| (*track_id, track))
+    }
+
+    // No library yet is not an error
+    pub fn load() -> std::io::Result<Self> {
+        match config_path("tracks.ftt") {
+            Some(path) if path.exists() => Self::read(&mut std::fs::File::open(path)?),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = config_path("tracks.ftt").ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "No home directory")
+        })?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let mut output = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.write(&mut output)?;
+        output.flush()
+    }
+
+    fn write(&self, output: &mut impl Write) -> std::io::Result<()> {
+        output.write_all(MAGIC)?;
+        output.write_all(&[VERSION])?;
+
+        output.write_all(&(self.tracks.len() as u32).to_le_bytes())?;
+        for (track_id, track) in &self.tracks {
+            output.write_all(&(*track_id as u64).to_le_bytes())?;
+            write_string(output, &track.name)?;
+
+            output.write_all(&[track.start.is_some() as u8])?;
+            output.write_all(&(track.sectors.len() as u32).to_le_bytes())?;
+            track
+                .start
+                .iter()
+                .chain(&track.sectors)
+                .try_for_each(|gate| write_gate(output, gate))?;
+
+            for best in track.best_sectors.iter().chain([&track.best_lap]) {
+                output.write_all(&best.unwrap_or(f32::NAN).to_le_bytes())?;
+            }
+
+            output.write_all(&(track.reference.len() as u32).to_le_bytes())?;
+            for (x, z) in &track.reference {
+                output.write_all(&x.to_le_bytes())?;
+                output.write_all(&z.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn read(input: &mut impl Read) -> std::io::Result<Self> {
+        let mut header = [0u8; 4];
+        input.read_exact(&mut header)?;
+        let version = header[3];
+        if &header[..3] != MAGIC || !(1..=VERSION).contains(&version) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Not a track library",
+            ));
+        }
+
+        let mut tracks = Self::default();
+        for _ in 0..read_u32(input)? {
+            let track_id = read_u64(input)? as TrackId;
+            let mut track = Track::new(read_string(input)?);
+
+            let has_start = read_u8(input)? != 0;
+            let sector_count = read_u32(input)?;
+            if has_start {
+                track.start = Some(read_gate(input)?);
+            }
+            for _ in 0..sector_count {
+                track.sectors.push(read_gate(input)?);
+            }
+
+            let mut read_best = || read_f32(input).map(|t| Some(t).filter(|t| !t.is_nan()));
+            track.best_sectors = (0..track.sector_count())
+                .map(|_| read_best())
+                .collect::<std::io::Result<_>>()?;
+            track.best_lap = read_best()?;
+
+            // Reference paths were introduced with the version 2
+            if version >= 2 {
+                for _ in 0..read_u32(input)? {
+                    track.reference.push((read_f32(input)?, read_f32(input)?));
+                }
+            }
+
+            tracks.tracks.insert(track_id, track);
+            tracks.next_id = tracks.next_id.max(track_id + 1);
+        }
+        Ok(tracks)
+    }
+}
+
+fn write_gate(output: &mut impl Write, gate: &Gate) -> std::io::Result<()> {
+    [gate.0 .0, gate.0 .1, gate.1 .0, gate.1 .1]
+        .iter()
+        .try_for_each(|v| output.write_all(&v.to_le_bytes()))
+}
+
+fn read_gate(input: &mut impl Read) -> std::io::Result<Gate> {
+    Ok(Gate(
+        (read_f32(input)?, read_f32(input)?),
+        (read_f32(input)?, read_f32(input)?),
+    ))
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+fn mean_distance(points: &[(f32, f32)], path: &[(f32, f32)]) -> f32 {
+    let sum: f32 = points.iter().map(|p| distance_to_path(path, *p)).sum();
+    sum / points.len() as f32
+}
+
+fn distance_to_path(path: &[(f32, f32)], point: (f32, f32)) -> f32 {
+    match path {
+        [single] => distance(*single, point),
+        path => path
+            .windows(2)
+            .map(|segment| segment_distance(segment[0], segment[1], point))
+            .fold(f32::INFINITY, f32::min),
+    }
+}
+
+fn segment_distance(a: (f32, f32), b: (f32, f32), point: (f32, f32)) -> f32 {
+    let (dx, dz) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dz * dz;
+    if length_sq == 0.0 {
+        return distance(a, point);
+    }
+
+    let t = (((point.0 - a.0) * dx + (point.1 - a.1) * dz) / length_sq).clamp(0.0, 1.0);
+    distance((a.0 + t * dx, a.1 + t * dz), point)
+}
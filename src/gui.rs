@@ -1,11 +1,47 @@
 pub mod chunk_panel;
 pub use chunk_panel::*;
 
+pub mod color_ramp;
+pub use color_ramp::*;
+
+pub mod compare_panel;
+pub use compare_panel::*;
+
 pub mod control_panel;
 pub use control_panel::*;
 
+pub mod dashboard_panel;
+pub use dashboard_panel::*;
+
+pub mod gg_panel;
+pub use gg_panel::*;
+
+pub mod histogram_panel;
+pub use histogram_panel::*;
+
+pub mod lap_panel;
+pub use lap_panel::*;
+
+pub mod lod_line;
+pub use lod_line::*;
+
 pub mod map_panel;
 pub use map_panel::*;
 
+pub mod map_trace;
+pub use map_trace::*;
+
 pub mod packet_panel;
 pub use packet_panel::*;
+
+pub mod playback_panel;
+pub use playback_panel::*;
+
+pub mod plot_panel;
+pub use plot_panel::*;
+
+pub mod tire_widget;
+pub use tire_widget::*;
+
+pub mod track_panel;
+pub use track_panel::*;
@@ -3,18 +3,47 @@ use crate::forza::{self, Lap};
 use crate::forza::{ChunkId, ChunkSelector, LapId};
 use eframe::egui;
 
+pub fn format_time(seconds: f32) -> String {
+    let minutes = (seconds / 60.0).floor();
+    format!("{}:{:06.3}", minutes, seconds - minutes * 60.0)
+}
+
 pub enum EventTypes {
     ChangeSelection(ChunkSelector),
     RemoveChunk(ChunkSelector),
+    SplitChunk(ChunkId, usize),
+    MergeChunks(ChunkId),
+    TrimChunk(ChunkId, std::ops::Range<usize>),
+    DropIdle(ChunkId),
+    SetMetadata(ChunkSelector, forza::Metadata),
+    ChangeOverlays,
+    Undo,
+    Redo,
 }
 type Events = event::Events<EventTypes>;
 
-#[derive(Default)]
 pub struct ChunkPanel {
     selection: ChunkSelector,
+    overlays: Vec<ChunkSelector>, // Also drawn on the map
+    edit_range: (usize, usize),
+    details: Option<(ChunkSelector, forza::Metadata, forza::Metadata, String)>,
+    filter: String,
     events: Events,
 }
 
+impl Default for ChunkPanel {
+    fn default() -> Self {
+        Self {
+            selection: ChunkSelector::default(),
+            overlays: Vec::new(),
+            edit_range: (0, usize::MAX),
+            details: None,
+            filter: String::new(),
+            events: Events::default(),
+        }
+    }
+}
+
 impl event::EventGenerator<EventTypes> for ChunkPanel {
     fn events(&mut self) -> &mut Events {
         &mut self.events
@@ -24,15 +53,51 @@ impl event::EventGenerator<EventTypes> for ChunkPanel {
 impl ChunkPanel {
     fn select(&mut self, chunk_id: ChunkId, lap_id: LapId) {
         self.selection = ChunkSelector(chunk_id, lap_id);
+        self.edit_range = (0, usize::MAX);
 
         self.gen_event(EventTypes::ChangeSelection(self.selection));
     }
 
+    // Ctrl-click adds to the overlays instead of selecting
+    fn click(&mut self, ui: &egui::Ui, chunk_id: ChunkId, lap_id: LapId) {
+        match ui.input().modifiers.command {
+            true => self.toggle_overlay(ChunkSelector(chunk_id, lap_id)),
+            false => self.select(chunk_id, lap_id),
+        }
+    }
+
+    fn toggle_overlay(&mut self, chunk_selector: ChunkSelector) {
+        match self.overlays.iter().position(|s| *s == chunk_selector) {
+            Some(position) => drop(self.overlays.remove(position)),
+            None => self.overlays.push(chunk_selector),
+        }
+        self.gen_event(EventTypes::ChangeOverlays);
+    }
+
+    fn show_overlay_checkbox(&mut self, ui: &mut egui::Ui, chunk_id: ChunkId, lap_id: LapId) {
+        let chunk_selector = ChunkSelector(chunk_id, lap_id);
+        let mut checked = self.overlays.contains(&chunk_selector);
+        if ui
+            .checkbox(&mut checked, "")
+            .on_hover_text("Overlay on the map (or ctrl-click)")
+            .changed()
+        {
+            self.toggle_overlay(chunk_selector);
+        }
+    }
+
+    pub fn overlays(&self) -> &[ChunkSelector] {
+        &self.overlays
+    }
+
     fn is_selected(&self, chunk_id: ChunkId, lap_id: LapId) -> bool {
         ChunkSelector(chunk_id, lap_id) == self.selection
     }
 
     pub fn set_selection(&mut self, chunk_selector: ChunkSelector) {
+        if chunk_selector != self.selection {
+            self.edit_range = (0, usize::MAX);
+        }
         self.selection = chunk_selector;
     }
 
@@ -41,11 +106,28 @@ impl ChunkPanel {
     }
 
     pub fn selected_packets<'a>(&self, chunks: &'a forza::Chunks) -> &'a [forza::Packet] {
+        chunks.packets_of(self.selection)
+    }
+
+    // The packets picked in the edit section, within the selected packets
+    pub fn edit_range(&self, chunks: &forza::Chunks) -> std::ops::Range<usize> {
+        let len = self.selected_packets(chunks).len();
+        let (begin, end) = self.edit_range;
+        begin.min(len)..end.saturating_add(1).min(len)
+    }
+
+    pub fn set_edit_range(&mut self, range: std::ops::Range<usize>) {
+        if !range.is_empty() {
+            self.edit_range = (range.start, range.end - 1);
+        }
+    }
+
+    pub fn selected_rewinds<'a>(&self, chunks: &'a forza::Chunks) -> Vec<&'a forza::Rewind> {
         let ChunkSelector(chunk_id, lap_id) = self.selection;
-        match (chunks.list().iter().nth(chunk_id), lap_id) {
-            (Some(selected_chunk), Some(lap)) => selected_chunk.lap_packets(lap),
-            (Some(selected_chunk), None) => &selected_chunk.packets,
-            (None, _) => &[],
+        match (chunks.get(chunk_id), lap_id) {
+            (Some(selected_chunk), Some(lap)) => selected_chunk.lap_rewinds(lap).collect(),
+            (Some(selected_chunk), None) => selected_chunk.rewinds.iter().collect(),
+            (None, _) => vec![],
         }
     }
 
@@ -53,13 +135,28 @@ impl ChunkPanel {
         self.gen_event(EventTypes::RemoveChunk(ChunkSelector(chunk_id, lap_id)));
     }
 
-    fn show_free_roam(&mut self, ui: &mut egui::Ui, chunk_id: ChunkId) {
+    fn hover_text(chunk: &forza::Chunk, lap_id: LapId) -> String {
+        let summary = chunk
+            .metadata_of(lap_id)
+            .map(|m| m.summary())
+            .unwrap_or_default();
+        match (lap_id, summary.is_empty()) {
+            (None, true) => chunk.split_reason.description().to_owned(),
+            (None, false) => format!("{}\n{}", chunk.split_reason.description(), summary),
+            (Some(_), _) => summary,
+        }
+    }
+
+    fn show_free_roam(&mut self, ui: &mut egui::Ui, chunk_id: ChunkId, chunk: &forza::Chunk) {
         ui.horizontal(|ui| {
+            self.show_overlay_checkbox(ui, chunk_id, None);
+            let title = chunk.metadata.name().unwrap_or("Free Roam");
             if ui
-                .selectable_label(self.is_selected(chunk_id, None), "Free Roam")
+                .selectable_label(self.is_selected(chunk_id, None), title)
+                .on_hover_text(Self::hover_text(chunk, None))
                 .clicked()
             {
-                self.select(chunk_id, None)
+                self.click(ui, chunk_id, None)
             }
 
             if ui.button("🗑").clicked() {
@@ -68,8 +165,52 @@ impl ChunkPanel {
         });
     }
 
-    fn show_race(&mut self, ui: &mut egui::Ui, chunk_id: usize, chunk: &forza::Chunk) {
-        let resp = egui::CollapsingHeader::new("Race")
+    // Purple for the best of the track, green for the best of the chunk
+    fn show_sector_times(
+        ui: &mut egui::Ui,
+        sector_times: &[Option<f32>],
+        chunk_bests: &[Option<f32>],
+        track: &forza::Track,
+    ) {
+        let is_best = |best: Option<&Option<f32>>, time: f32| {
+            best.copied()
+                .flatten()
+                .is_some_and(|best| time <= best + 1e-4)
+        };
+
+        for (sector_num, time) in sector_times.iter().enumerate() {
+            let time = match time {
+                Some(time) => *time,
+                None => continue,
+            };
+            let color = if is_best(track.best_sectors.get(sector_num), time) {
+                egui::Color32::from_rgb(180, 100, 255)
+            } else if is_best(chunk_bests.get(sector_num), time) {
+                egui::Color32::from_rgb(80, 220, 100)
+            } else {
+                egui::Color32::from_rgb(240, 210, 80)
+            };
+            ui.colored_label(color, format!("{:.3}", time))
+                .on_hover_text(format!("Sector {}", sector_num + 1));
+        }
+    }
+
+    fn show_race(
+        &mut self,
+        ui: &mut egui::Ui,
+        chunk_id: ChunkId,
+        chunk: &forza::Chunk,
+        title: &str,
+        tracks: &forza::Tracks,
+    ) {
+        let track = chunk.track.and_then(|track_id| tracks.get(track_id));
+        let chunk_bests = chunk.best_sector_times();
+        let title = match track {
+            Some(track) => format!("{} - {}", title, track.name),
+            None => title.to_owned(),
+        };
+        let show_all_laps = chunk.metadata.matches(&self.filter);
+        let resp = egui::CollapsingHeader::new(chunk.metadata.name().unwrap_or(&title))
             .id_source(chunk_id)
             .selectable(true)
             .selected(self.is_selected(chunk_id, None))
@@ -80,15 +221,44 @@ impl ChunkPanel {
                     // if *lap_num < replace(&mut last_lap, *lap_num) {
                     //     continue;
                     // }
+                    if !show_all_laps && !chunk.lap_matches(*lap_num, &self.filter) {
+                        continue;
+                    }
+
+                    let mut title = match chunk.lap_metadata.get(lap_num).and_then(|m| m.name()) {
+                        Some(name) => format!("Lap {} - {}", lap_num + 1, name),
+                        None => format!("Lap {}", lap_num + 1),
+                    };
+                    if let Some(lap_time) = chunk.virtual_lap_time(*lap_num) {
+                        title = format!("{} ({})", title, format_time(lap_time));
+                    }
                     ui.horizontal(|ui| {
-                        if ui
-                            .selectable_label(
-                                self.is_selected(chunk_id, Some(*lap_num)),
-                                format!("Lap {}", lap_num + 1),
-                            )
-                            .clicked()
+                        self.show_overlay_checkbox(ui, chunk_id, Some(*lap_num));
+                        let mut label =
+                            ui.selectable_label(self.is_selected(chunk_id, Some(*lap_num)), title);
+                        if chunk.lap_metadata.contains_key(lap_num) {
+                            label = label.on_hover_text(Self::hover_text(chunk, Some(*lap_num)));
+                        }
+                        if label.clicked() {
+                            self.click(ui, chunk_id, Some(*lap_num));
+                        }
+
+                        let rewinds = chunk.lap_rewinds(*lap_num).count();
+                        if rewinds > 0 {
+                            ui.label(format!("⟲{}", rewinds)).on_hover_text(format!(
+                                "Rewound {} times, {} packets discarded",
+                                rewinds,
+                                chunk
+                                    .lap_rewinds(*lap_num)
+                                    .map(|r| r.discarded)
+                                    .sum::<usize>()
+                            ));
+                        }
+
+                        if let (Some(track), Some(sector_times)) =
+                            (track, chunk.lap_sector_times(*lap_num))
                         {
-                            self.select(chunk_id, Some(*lap_num));
+                            Self::show_sector_times(ui, sector_times, &chunk_bests, track);
                         }
 
                         if ui.button("🗑").clicked() {
@@ -97,29 +267,210 @@ impl ChunkPanel {
                     });
                 }
             })
-            .header_response;
+            .header_response
+            .on_hover_text(Self::hover_text(chunk, None));
 
         if resp.clicked() {
-            self.select(chunk_id, None);
+            self.click(ui, chunk_id, None);
         }
     }
 
-    pub fn show(&mut self, ctx: &egui::CtxRef, chunks: &forza::Chunks) {
+    pub fn show(&mut self, ctx: &egui::CtxRef, chunks: &forza::Chunks, tracks: &forza::Tracks) {
         egui::Window::new("Chunk").show(ctx, |ui| {
+            self.show_history(ui, chunks);
+
+            ui.horizontal(|ui| {
+                ui.label("🔍");
+                ui.text_edit_singleline(&mut self.filter)
+                    .on_hover_text("Filter by name, notes, #tags, driver or setup");
+            });
+
             egui::ScrollArea::vertical().show(ui, |ui| {
                 let mut packets_count = 0usize;
 
-                for (chunk_id, chunk) in chunks.list().iter().enumerate() {
+                for (chunk_id, chunk) in chunks.iter() {
                     packets_count += chunk.packets.len();
+                    if !chunk.matches(&self.filter) {
+                        continue;
+                    }
+
                     match chunk.game_mode() {
-                        forza::GameMode::FreeRoam => self.show_free_roam(ui, chunk_id),
-                        forza::GameMode::Race => self.show_race(ui, chunk_id, &chunk),
-                        _ => self.show_free_roam(ui, chunk_id),
+                        forza::GameMode::FreeRoam if chunk.lap_count() > 1 => {
+                            self.show_race(ui, chunk_id, chunk, "Free Roam", tracks)
+                        }
+                        forza::GameMode::FreeRoam => self.show_free_roam(ui, chunk_id, chunk),
+                        forza::GameMode::Race => {
+                            self.show_race(ui, chunk_id, chunk, "Race", tracks)
+                        }
+                        _ => self.show_free_roam(ui, chunk_id, chunk),
                     }
                 }
 
                 ui.label(format!("Packets: {}", packets_count));
             });
+
+            egui::CollapsingHeader::new("Details")
+                .default_open(false)
+                .show(ui, |ui| self.show_details(ui, chunks));
+
+            egui::CollapsingHeader::new("Edit")
+                .default_open(false)
+                .show(ui, |ui| self.show_edit(ui, chunks));
+        });
+    }
+
+    fn show_history(&mut self, ui: &mut egui::Ui, chunks: &forza::Chunks) {
+        ui.horizontal(|ui| {
+            let undo = ui.add_enabled(chunks.undo_name().is_some(), egui::Button::new("↶ Undo"));
+            if undo
+                .on_hover_text(chunks.undo_name().unwrap_or_default())
+                .clicked()
+            {
+                self.gen_event(EventTypes::Undo);
+            }
+
+            let redo = ui.add_enabled(chunks.redo_name().is_some(), egui::Button::new("↷ Redo"));
+            if redo
+                .on_hover_text(chunks.redo_name().unwrap_or_default())
+                .clicked()
+            {
+                self.gen_event(EventTypes::Redo);
+            }
+        });
+        ui.separator();
+    }
+
+    fn show_details(&mut self, ui: &mut egui::Ui, chunks: &forza::Chunks) {
+        let selection = self.selection;
+        let ChunkSelector(chunk_id, lap_id) = selection;
+        let chunk = match chunks.get(chunk_id) {
+            Some(chunk) => chunk,
+            None => return,
+        };
+
+        // Edit a copy until the changes are applied, reload it on undo/redo
+        let original = chunk.metadata_of(lap_id).cloned().unwrap_or_default();
+        if self.details.as_ref().map(|d| (d.0, &d.1)) != Some((selection, &original)) {
+            let tags = original.tags.join(", ");
+            self.details = Some((selection, original.clone(), original.clone(), tags));
+        }
+        let (_, _, metadata, tags) = self.details.as_mut().unwrap();
+
+        egui::Grid::new("Details").num_columns(2).show(ui, |ui| {
+            ui.label("Name");
+            ui.text_edit_singleline(&mut metadata.name);
+            ui.end_row();
+
+            ui.label("Driver");
+            ui.text_edit_singleline(&mut metadata.driver);
+            ui.end_row();
+
+            ui.label("Setup");
+            ui.text_edit_singleline(&mut metadata.setup);
+            ui.end_row();
+
+            ui.label("Tags");
+            ui.text_edit_singleline(tags);
+            ui.end_row();
+
+            ui.label("Notes");
+            ui.text_edit_multiline(&mut metadata.notes);
+            ui.end_row();
+        });
+
+        let mut metadata = metadata.clone();
+        metadata.tags = forza::Metadata::parse_tags(tags);
+        let changed = metadata != original;
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(changed, egui::Button::new("Apply"))
+                .clicked()
+            {
+                self.gen_event(EventTypes::SetMetadata(selection, metadata));
+            }
+            if ui
+                .add_enabled(changed, egui::Button::new("Revert"))
+                .clicked()
+            {
+                self.details = None;
+            }
+        });
+    }
+
+    fn show_edit(&mut self, ui: &mut egui::Ui, chunks: &forza::Chunks) {
+        let ChunkSelector(chunk_id, lap_id) = self.selection;
+        let (chunk, packets) = match chunks.get(chunk_id) {
+            Some(chunk) => (chunk, self.selected_packets(chunks)),
+            None => return,
+        };
+        if packets.is_empty() {
+            return;
+        }
+
+        // Offset of the selected packets within the chunk
+        let offset = lap_id
+            .and_then(|lap| chunk.lap_span(lap))
+            .map(|span| span.start)
+            .unwrap_or(0);
+
+        let last = packets.len() - 1;
+        let (begin, end) = &mut self.edit_range;
+        *begin = (*begin).min(last);
+        *end = (*end).min(last);
+
+        let time_of = |index: usize| {
+            packets[index]
+                .timestamp_ms
+                .saturating_sub(packets[0].timestamp_ms) as f32
+                / 1000.0
+        };
+
+        ui.horizontal(|ui| {
+            ui.add(egui::Slider::new(begin, 0..=last).show_value(false));
+            ui.label(format!("Start: {:.1} s", time_of(*begin)));
+        });
+        ui.horizontal(|ui| {
+            ui.add(egui::Slider::new(end, 0..=last).show_value(false));
+            ui.label(format!("End: {:.1} s", time_of(*end)));
+        });
+        *end = (*end).max(*begin);
+        let (begin, end) = (offset + *begin, offset + *end);
+
+        ui.horizontal(|ui| {
+            if ui
+                .button("Split at start")
+                .on_hover_text("Start a new chunk at the start packet")
+                .clicked()
+            {
+                self.gen_event(EventTypes::SplitChunk(chunk_id, begin));
+            }
+
+            if ui
+                .button("Trim")
+                .on_hover_text("Only keep the packets between start and end")
+                .clicked()
+            {
+                self.gen_event(EventTypes::TrimChunk(chunk_id, begin..end + 1));
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui
+                .button("Merge with next")
+                .on_hover_text("Append the next chunk to this one")
+                .clicked()
+            {
+                self.gen_event(EventTypes::MergeChunks(chunk_id));
+            }
+
+            if ui
+                .button("Drop idle")
+                .on_hover_text("Remove the stretches where the car stands still")
+                .clicked()
+            {
+                self.gen_event(EventTypes::DropIdle(chunk_id));
+            }
         });
     }
 }
@@ -0,0 +1,57 @@
+use eframe::egui::Color32;
+
+#[derive(PartialEq, Clone, Copy, Default)]
+pub enum ColorRamp {
+    #[default]
+    Heat,
+    Viridis,
+    RedGreen,
+}
+
+impl ColorRamp {
+    pub const ALL: [ColorRamp; 3] = [ColorRamp::Heat, ColorRamp::Viridis, ColorRamp::RedGreen];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorRamp::Heat => "Heat",
+            ColorRamp::Viridis => "Viridis",
+            ColorRamp::RedGreen => "Red-green",
+        }
+    }
+
+    fn stops(&self) -> &'static [(u8, u8, u8)] {
+        match self {
+            ColorRamp::Heat => &[(40, 40, 255), (40, 220, 220), (240, 220, 40), (255, 40, 40)],
+            ColorRamp::Viridis => &[
+                (68, 1, 84),
+                (59, 82, 139),
+                (33, 145, 140),
+                (94, 201, 98),
+                (253, 231, 37),
+            ],
+            ColorRamp::RedGreen => &[(220, 40, 40), (240, 220, 40), (40, 200, 80)],
+        }
+    }
+
+    // Colour of a value between 0 and 1
+    pub fn color(&self, t: f32) -> Color32 {
+        let stops = self.stops();
+        let position = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let index = (position as usize).min(stops.len() - 2);
+        let (a, b, t) = (stops[index], stops[index + 1], position - index as f32);
+
+        let lerp = |a: u8, b: u8| (a as f32 + t * (b as f32 - a as f32)).round() as u8;
+        Color32::from_rgb(lerp(a.0, b.0), lerp(a.1, b.1), lerp(a.2, b.2))
+    }
+
+    // Black or white, whichever reads better over the colour of a value
+    pub fn text_color(&self, t: f32) -> Color32 {
+        let color = self.color(t);
+        let luminance =
+            0.2126 * color.r() as f32 + 0.7152 * color.g() as f32 + 0.0722 * color.b() as f32;
+        match luminance > 128.0 {
+            true => Color32::BLACK,
+            false => Color32::WHITE,
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use crate::event::{self, EventGenerator};
+use crate::forza::{self, ChunkSelector, Comparison};
+use eframe::egui;
+
+use egui::plot::{self, Value, Values};
+
+pub const REFERENCE_COLOR: egui::Color32 = egui::Color32::from_rgb(80, 200, 255);
+const OTHER_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 48, 134);
+
+pub enum EventTypes {
+    ChangeReference,
+}
+type Events = event::Events<EventTypes>;
+
+#[derive(Default)]
+pub struct ComparePanel {
+    reference: Option<ChunkSelector>,
+    comparison: Comparison,
+    comparison_key: Option<(ChunkSelector, usize, ChunkSelector, usize)>,
+    cursor: Option<f64>, // Distance hovered in one of the plots
+    events: Events,
+}
+
+impl event::EventGenerator<EventTypes> for ComparePanel {
+    fn events(&mut self) -> &mut Events {
+        &mut self.events
+    }
+}
+
+pub fn selection_label(chunks: &forza::Chunks, selection: ChunkSelector) -> String {
+    let ChunkSelector(chunk_id, lap_id) = selection;
+    let chunk = match chunks.get(chunk_id) {
+        Some(chunk) => chunk,
+        None => return "Removed".to_owned(),
+    };
+
+    let chunk_name = match chunk.metadata.name() {
+        Some(name) => name.to_owned(),
+        None => format!("Chunk {}", chunk_id + 1),
+    };
+    match lap_id {
+        Some(lap_num) => format!("{} / Lap {}", chunk_name, lap_num + 1),
+        None => chunk_name,
+    }
+}
+
+impl ComparePanel {
+    pub fn reference_packets<'a>(&self, chunks: &'a forza::Chunks) -> &'a [forza::Packet] {
+        match self.reference {
+            Some(reference) => chunks.packets_of(reference),
+            None => &[],
+        }
+    }
+
+    fn update_comparison(&mut self, chunks: &forza::Chunks, selection: ChunkSelector) {
+        let reference = match self.reference {
+            Some(reference) => reference,
+            None => {
+                self.comparison_key = None;
+                self.comparison = Comparison::default();
+                return;
+            }
+        };
+
+        let (reference_packets, packets) =
+            (chunks.packets_of(reference), chunks.packets_of(selection));
+        let key = Some((reference, reference_packets.len(), selection, packets.len()));
+        if key != self.comparison_key {
+            self.comparison_key = key;
+            self.comparison = Comparison::new(reference_packets, packets);
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::CtxRef, chunks: &forza::Chunks, selection: ChunkSelector) {
+        self.update_comparison(chunks, selection);
+
+        egui::Window::new("Compare").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let label = match self.reference {
+                    Some(reference) => selection_label(chunks, reference),
+                    None => "None".to_owned(),
+                };
+                ui.label("Reference:");
+                ui.colored_label(REFERENCE_COLOR, label);
+
+                if ui
+                    .button("Use selection")
+                    .on_hover_text("Compare the next selections with this lap")
+                    .clicked()
+                {
+                    self.reference = Some(selection);
+                    self.gen_event(EventTypes::ChangeReference);
+                }
+                if self.reference.is_some() && ui.button("Clear").clicked() {
+                    self.reference = None;
+                    self.gen_event(EventTypes::ChangeReference);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Compared:");
+                ui.colored_label(OTHER_COLOR, selection_label(chunks, selection));
+            });
+
+            if self.comparison.is_empty() {
+                return;
+            }
+            if let Some(delta) = self.comparison.delta.last() {
+                ui.label(format!(
+                    "Gap after {:.0} m: {:+.3} s",
+                    self.comparison.distance.last().unwrap(),
+                    delta
+                ));
+            }
+            ui.separator();
+
+            self.show_plots(ui);
+        });
+    }
+
+    fn show_plots(&mut self, ui: &mut egui::Ui) {
+        let comparison = &self.comparison;
+        let line = |values: Vec<f32>, color: egui::Color32, name: &str| {
+            let values = comparison
+                .distance
+                .iter()
+                .zip(values)
+                .map(|(d, v)| Value::new(*d, v));
+            plot::Line::new(Values::from_values_iter(values))
+                .color(color)
+                .name(name)
+        };
+        let lines = |value: &dyn Fn(&forza::Sample) -> f32| {
+            [
+                line(
+                    comparison.reference.iter().map(value).collect(),
+                    REFERENCE_COLOR,
+                    "Reference",
+                ),
+                line(
+                    comparison.other.iter().map(value).collect(),
+                    OTHER_COLOR,
+                    "Compared",
+                ),
+            ]
+        };
+
+        let mut cursor = None;
+        let mut show_plot = |ui: &mut egui::Ui, title: &str, lines: Vec<plot::Line>| {
+            ui.label(title);
+            plot::Plot::new(title)
+                .height(100.0)
+                .include_y(0.0)
+                .show(ui, |plot_ui| {
+                    if plot_ui.plot_hovered() {
+                        cursor = plot_ui.pointer_coordinate().map(|c| c.x);
+                    }
+                    lines.into_iter().for_each(|line| plot_ui.line(line));
+                    if let Some(x) = self.cursor {
+                        plot_ui.vline(plot::VLine::new(x).color(egui::Color32::WHITE));
+                    }
+                });
+        };
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            show_plot(
+                ui,
+                "Delta (s)",
+                vec![line(
+                    comparison.delta.clone(),
+                    egui::Color32::WHITE,
+                    "Delta",
+                )],
+            );
+            show_plot(ui, "Speed (km/h)", Vec::from(lines(&|s| s.speed * 3.6)));
+            show_plot(
+                ui,
+                "Throttle (%)",
+                Vec::from(lines(&|s| s.accel as f32 / 2.55)),
+            );
+            show_plot(
+                ui,
+                "Brake (%)",
+                Vec::from(lines(&|s| s.brake as f32 / 2.55)),
+            );
+            show_plot(ui, "Gear", Vec::from(lines(&|s| s.gear as f32)));
+        });
+        self.cursor = cursor;
+    }
+}
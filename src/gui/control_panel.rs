@@ -4,6 +4,7 @@ use egui::{CtxRef, Ui};
 use crate::{
     dialog,
     event::{self, EventGenerator},
+    forza::{GameMode, Packet, SplitRules},
 };
 
 pub enum EventTypes {
@@ -12,10 +13,41 @@ pub enum EventTypes {
 }
 type Events = event::Events<EventTypes>;
 
+#[derive(PartialEq, Clone, Copy, Default)]
+pub enum RecordMode {
+    #[default]
+    Everything,
+    RaceOnly,
+    AutoRace,
+    Laps(u16),
+}
+
+impl RecordMode {
+    fn label(&self) -> &'static str {
+        match self {
+            RecordMode::Everything => "Everything",
+            RecordMode::RaceOnly => "Race only",
+            RecordMode::AutoRace => "Next race",
+            RecordMode::Laps(_) => "Next laps",
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum Trigger {
+    // Waiting for the race (or the next lap) to begin
+    Armed,
+    // Recording until the race finishes or enough laps are done
+    Running { first_lap: u16 },
+}
+
 #[derive(Default)]
 pub struct ControlPanel {
     record: bool,
-    next_race: bool,
+    mode: RecordMode,
+    trigger: Option<Trigger>,
+    last_packet: Option<(GameMode, u16)>,
+    split_rules: SplitRules,
     events: Events,
 }
 
@@ -30,12 +62,70 @@ impl ControlPanel {
         self.record
     }
 
-    pub fn want_next_race(&self) -> bool {
-        self.record
+    pub fn split_rules(&self) -> SplitRules {
+        self.split_rules
     }
 
-    pub fn start_race(&mut self) {
+    pub fn start_record(&mut self) {
         self.record = true;
+        self.trigger = match self.mode {
+            RecordMode::Everything | RecordMode::RaceOnly => None,
+            RecordMode::AutoRace | RecordMode::Laps(_) => Some(Trigger::Armed),
+        };
+    }
+
+    pub fn stop_record(&mut self) {
+        self.record = false;
+        self.trigger = None;
+    }
+
+    // Decide if a live packet must be recorded, updating the triggers.
+    pub fn want_packet(&mut self, packet: &Packet) -> bool {
+        let (game_mode, lap_number) = (packet.game_mode(), packet.lap_number);
+        let new_lap = match self.last_packet.replace((game_mode, lap_number)) {
+            Some((GameMode::Race, last_lap)) => {
+                game_mode == GameMode::Race && last_lap != lap_number
+            }
+            _ => game_mode == GameMode::Race,
+        };
+
+        if !self.record {
+            return false;
+        }
+
+        match (self.mode, self.trigger) {
+            (RecordMode::Everything, _) => true,
+            (RecordMode::RaceOnly, _) => game_mode == GameMode::Race,
+            (RecordMode::AutoRace, Some(Trigger::Armed)) => {
+                if game_mode == GameMode::Race && lap_number == 0 {
+                    self.trigger = Some(Trigger::Running { first_lap: 0 });
+                    true
+                } else {
+                    false
+                }
+            }
+            (RecordMode::Laps(_), Some(Trigger::Armed)) => {
+                if new_lap {
+                    self.trigger = Some(Trigger::Running {
+                        first_lap: lap_number,
+                    });
+                }
+                new_lap
+            }
+            (RecordMode::AutoRace, Some(Trigger::Running { .. })) => {
+                if game_mode != GameMode::Race {
+                    self.stop_record();
+                }
+                self.record
+            }
+            (RecordMode::Laps(count), Some(Trigger::Running { first_lap })) => {
+                if game_mode != GameMode::Race || lap_number >= first_lap.saturating_add(count) {
+                    self.stop_record();
+                }
+                self.record
+            }
+            (_, None) => false,
+        }
     }
 
     pub fn show(&mut self, ctx: &CtxRef) {
@@ -51,11 +141,49 @@ impl ControlPanel {
 
                 ui.horizontal(|ui| {
                     self.render_record_button(ui);
-                    self.render_next_race_button(ui);
+                    self.render_mode_combo(ui);
                 });
+
+                self.render_trigger_state(ui);
+
+                egui::CollapsingHeader::new("Split rules")
+                    .default_open(false)
+                    .show(ui, |ui| self.render_split_rules(ui));
             });
     }
 
+    fn render_split_rules(&mut self, ui: &mut Ui) {
+        let rules = &mut self.split_rules;
+        ui.checkbox(&mut rules.car_change, "Car change");
+        ui.checkbox(&mut rules.race_restart, "Race restart");
+
+        ui.horizontal(|ui| {
+            let mut enabled = rules.position_jump.is_some();
+            ui.checkbox(&mut enabled, "Position jump");
+            let mut jump = rules.position_jump.unwrap_or(200.0);
+            ui.add_enabled(
+                enabled,
+                egui::DragValue::new(&mut jump)
+                    .clamp_range(10.0..=5000.0)
+                    .suffix(" m"),
+            );
+            rules.position_jump = enabled.then_some(jump);
+        });
+
+        ui.horizontal(|ui| {
+            let mut enabled = rules.time_gap.is_some();
+            ui.checkbox(&mut enabled, "Pause");
+            let mut gap = rules.time_gap.unwrap_or(30_000) as f32 / 1000.0;
+            ui.add_enabled(
+                enabled,
+                egui::DragValue::new(&mut gap)
+                    .clamp_range(1.0..=3600.0)
+                    .suffix(" s"),
+            );
+            rules.time_gap = enabled.then_some((gap * 1000.0) as u32);
+        });
+    }
+
     fn render_record_button(&mut self, ui: &mut Ui) {
         let title = match self.record {
             true => "Stop",
@@ -63,15 +191,73 @@ impl ControlPanel {
         };
 
         if ui.button(title).clicked() {
-            self.record = !self.record;
+            match self.record {
+                true => self.stop_record(),
+                false => self.start_record(),
+            }
         }
     }
 
-    fn render_next_race_button(&mut self, ui: &mut Ui) {
-        ui.add_enabled(
-            !self.record,
-            egui::Checkbox::new(&mut self.next_race, "Race only"),
-        );
+    fn render_mode_combo(&mut self, ui: &mut Ui) {
+        let record = self.record;
+        let mode = &mut self.mode;
+        ui.add_enabled_ui(!record, |ui| {
+            egui::ComboBox::from_id_source("Record mode")
+                .selected_text(mode.label())
+                .show_ui(ui, |ui| {
+                    let laps = match *mode {
+                        RecordMode::Laps(count) => count,
+                        _ => 1,
+                    };
+                    for choice in [
+                        RecordMode::Everything,
+                        RecordMode::RaceOnly,
+                        RecordMode::AutoRace,
+                        RecordMode::Laps(laps),
+                    ] {
+                        ui.selectable_value(mode, choice, choice.label());
+                    }
+                });
+
+            if let RecordMode::Laps(count) = mode {
+                ui.add(
+                    egui::DragValue::new(count)
+                        .clamp_range(1..=99)
+                        .suffix(" laps"),
+                );
+            }
+        });
+    }
+
+    fn render_trigger_state(&mut self, ui: &mut Ui) {
+        let state = match (self.record, self.mode, self.trigger) {
+            (false, _, _) => "Idle".to_owned(),
+            (true, RecordMode::Everything, _) => "Recording everything".to_owned(),
+            (true, RecordMode::RaceOnly, _) => "Recording races only".to_owned(),
+            (true, RecordMode::AutoRace, Some(Trigger::Armed)) => {
+                "Armed: waiting for a race to start".to_owned()
+            }
+            (true, RecordMode::AutoRace, _) => "Recording until the race finishes".to_owned(),
+            (true, RecordMode::Laps(count), Some(Trigger::Armed)) => {
+                format!("Armed: waiting for the next lap, then {} laps", count)
+            }
+            (true, RecordMode::Laps(count), Some(Trigger::Running { first_lap })) => {
+                let current_lap = self.last_packet.map(|(_, lap)| lap).unwrap_or(first_lap);
+                format!(
+                    "Recording lap {}/{}",
+                    1 + current_lap.saturating_sub(first_lap),
+                    count
+                )
+            }
+            (true, RecordMode::Laps(_), None) => "Idle".to_owned(),
+        };
+
+        let color = match (self.record, self.trigger) {
+            (false, _) => ui.visuals().weak_text_color(),
+            (true, Some(Trigger::Armed)) => egui::Color32::from_rgb(250, 181, 127),
+            (true, _) => egui::Color32::from_rgb(255, 48, 134),
+        };
+        ui.colored_label(color, state);
     }
 
     fn render_load_button(&mut self, ui: &mut Ui) {
@@ -0,0 +1,226 @@
+use std::collections::VecDeque;
+
+use crate::forza::{self, Channel};
+use crate::gui::{format_time, TireWidget};
+use eframe::egui;
+
+use egui::plot::{self, Value, Values};
+
+const HISTORY_MS: u32 = 30_000;
+const REDLINE: f32 = 0.9; // Part of the max RPM where the redline starts
+
+const THROTTLE_COLOR: egui::Color32 = egui::Color32::from_rgb(80, 220, 100);
+const BRAKE_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 60, 60);
+const CLUTCH_COLOR: egui::Color32 = egui::Color32::from_rgb(80, 200, 255);
+const RPM_COLOR: egui::Color32 = egui::Color32::from_rgb(240, 210, 80);
+
+pub struct DashboardPanel {
+    live: bool, // Repaint when packets arrive
+    history: VecDeque<forza::Packet>,
+    tires: TireWidget,
+}
+
+impl Default for DashboardPanel {
+    fn default() -> Self {
+        Self {
+            live: true,
+            history: VecDeque::new(),
+            tires: TireWidget::default(),
+        }
+    }
+}
+
+fn bar(ui: &mut egui::Ui, label: &str, value: f32, color: egui::Color32) {
+    ui.label(label);
+    let size = egui::Vec2::new(ui.available_width().max(100.0), 14.0);
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter();
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+    let mut fill = rect;
+    fill.set_width(rect.width() * value.clamp(0.0, 1.0));
+    painter.rect_filled(fill, 2.0, color);
+    ui.end_row();
+}
+
+// Steering is drawn from the middle of the bar
+fn steer_bar(ui: &mut egui::Ui, steer: f32) {
+    ui.label("Steer");
+    let size = egui::Vec2::new(ui.available_width().max(100.0), 14.0);
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter();
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+    let center = rect.center().x;
+    let end = center + steer.clamp(-1.0, 1.0) * rect.width() / 2.0;
+    let fill = egui::Rect::from_x_y_ranges(center.min(end)..=center.max(end), rect.y_range());
+    painter.rect_filled(fill, 0.0, ui.visuals().text_color());
+    ui.end_row();
+}
+
+impl DashboardPanel {
+    pub fn push(&mut self, packet: &forza::Packet) {
+        // A new session restarts the clock of the game
+        if self
+            .history
+            .back()
+            .is_some_and(|last| packet.timestamp_ms < last.timestamp_ms)
+        {
+            self.history.clear();
+        }
+
+        self.history.push_back(packet.clone());
+        while self
+            .history
+            .front()
+            .is_some_and(|first| packet.timestamp_ms - first.timestamp_ms > HISTORY_MS)
+        {
+            self.history.pop_front();
+        }
+    }
+
+    pub fn is_live(&self) -> bool {
+        self.live
+    }
+
+    pub fn show(&mut self, ctx: &egui::CtxRef) {
+        egui::Window::new("Dashboard").show(ctx, |ui| {
+            ui.checkbox(&mut self.live, "Live")
+                .on_hover_text("Refresh as the game sends packets, even without mouse input");
+
+            let packet = match self.history.back() {
+                Some(packet) => packet,
+                None => {
+                    ui.label("Waiting for packets...");
+                    return;
+                }
+            };
+
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::Label::new(format!("{:.0} km/h", Channel::Speed.value(packet))).heading(),
+                );
+                ui.separator();
+                let gear = match packet.gear {
+                    0 => "R".to_owned(),
+                    gear => gear.to_string(),
+                };
+                ui.add(egui::Label::new(format!("Gear {}", gear)).heading());
+            });
+
+            Self::show_rpm(ui, packet);
+            ui.separator();
+
+            egui::Grid::new("Dashboard inputs")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    bar(ui, "Throttle", packet.accel as f32 / 255.0, THROTTLE_COLOR);
+                    bar(ui, "Brake", packet.brake as f32 / 255.0, BRAKE_COLOR);
+                    bar(ui, "Clutch", packet.clutch as f32 / 255.0, CLUTCH_COLOR);
+                    steer_bar(ui, packet.steer as f32 / 127.0);
+                });
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                Self::show_laps(ui, packet);
+                ui.separator();
+                ui.vertical(|ui| self.tires.show(ui, packet));
+            });
+            ui.separator();
+
+            Self::show_strip_chart(ui, &self.history);
+        });
+    }
+
+    fn show_rpm(ui: &mut egui::Ui, packet: &forza::Packet) {
+        let max_rpm = packet.engine_max_rpm.max(1.0);
+        let fraction = packet.current_engine_rpm / max_rpm;
+        ui.label(format!(
+            "{:.0} / {:.0} rpm",
+            packet.current_engine_rpm, packet.engine_max_rpm
+        ));
+
+        let size = egui::Vec2::new(ui.available_width().max(200.0), 20.0);
+        let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+        let painter = ui.painter();
+        painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+        let mut redline = rect;
+        redline.set_left(rect.left() + rect.width() * REDLINE);
+        painter.rect_filled(redline, 2.0, BRAKE_COLOR.linear_multiply(0.3));
+
+        let color = match fraction >= REDLINE {
+            true => BRAKE_COLOR,
+            false => RPM_COLOR,
+        };
+        let mut fill = rect;
+        fill.set_width(rect.width() * fraction.clamp(0.0, 1.0));
+        painter.rect_filled(fill, 2.0, color);
+    }
+
+    fn show_laps(ui: &mut egui::Ui, packet: &forza::Packet) {
+        // The game reports zero until a lap is completed
+        let lap_time = |time: f32| match time > 0.0 {
+            true => format_time(time),
+            false => "-".to_owned(),
+        };
+
+        egui::Grid::new("Dashboard laps").show(ui, |ui| {
+            ui.label("Lap");
+            ui.label(format!("{}", packet.lap_number + 1));
+            ui.end_row();
+            ui.label("Current");
+            ui.label(lap_time(packet.current_lap));
+            ui.end_row();
+            ui.label("Last");
+            ui.label(lap_time(packet.last_lap));
+            ui.end_row();
+            ui.label("Best");
+            ui.colored_label(
+                egui::Color32::from_rgb(180, 100, 255),
+                lap_time(packet.best_lap),
+            );
+            ui.end_row();
+        });
+    }
+
+    fn show_strip_chart(ui: &mut egui::Ui, history: &VecDeque<forza::Packet>) {
+        let now = match history.back() {
+            Some(packet) => packet.timestamp_ms,
+            None => return,
+        };
+        let line = |channel: Channel, color: egui::Color32| {
+            let values = history.iter().map(|p| {
+                let time = -((now - p.timestamp_ms) as f64 / 1000.0);
+                Value::new(time, channel.value(p))
+            });
+            plot::Line::new(Values::from_values_iter(values))
+                .color(color)
+                .name(channel.name())
+        };
+
+        let duration = -(HISTORY_MS as f64 / 1000.0);
+        plot::Plot::new("Dashboard speed")
+            .height(80.0)
+            .allow_drag(false)
+            .allow_zoom(false)
+            .include_x(duration)
+            .include_x(0.0)
+            .include_y(0.0)
+            .show(ui, |plot_ui| {
+                plot_ui.line(line(Channel::Speed, RPM_COLOR));
+            });
+        plot::Plot::new("Dashboard pedals")
+            .height(80.0)
+            .allow_drag(false)
+            .allow_zoom(false)
+            .include_x(duration)
+            .include_x(0.0)
+            .include_y(0.0)
+            .include_y(100.0)
+            .show(ui, |plot_ui| {
+                plot_ui.line(line(Channel::Throttle, THROTTLE_COLOR));
+                plot_ui.line(line(Channel::Brake, BRAKE_COLOR));
+            });
+    }
+}
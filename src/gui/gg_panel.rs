@@ -0,0 +1,214 @@
+use crate::forza::{self, Channel};
+use crate::gui::{overlay_color, ColorRamp};
+use eframe::egui;
+
+use egui::plot::{self, Value, Values};
+
+const COLOR_STEPS: usize = 16;
+const PERCENTILES: [f32; 3] = [0.9, 0.95, 0.99];
+const SELECTION_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 48, 134);
+
+#[derive(PartialEq, Clone, Copy)]
+enum Coloring {
+    Speed,
+    Gear,
+    Layer,
+}
+
+impl Coloring {
+    const ALL: [Coloring; 3] = [Coloring::Speed, Coloring::Gear, Coloring::Layer];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Coloring::Speed => "Speed",
+            Coloring::Gear => "Gear",
+            Coloring::Layer => "Lap",
+        }
+    }
+}
+
+// The g-forces of a lap, and their envelope
+struct Layer {
+    name: String,
+    points: Vec<(f32, f32)>,
+    envelope: Vec<(f32, f32)>,
+}
+
+pub struct GgPanel {
+    coloring: Coloring,
+    percentile: f32,
+    layers: Vec<Layer>,
+    layers_key: Vec<(String, usize, f32)>, // What the layers describe
+}
+
+impl Default for GgPanel {
+    fn default() -> Self {
+        Self {
+            coloring: Coloring::Speed,
+            percentile: 0.95,
+            layers: Vec::new(),
+            layers_key: Vec::new(),
+        }
+    }
+}
+
+fn g_forces(packet: &forza::Packet) -> (f32, f32) {
+    (
+        Channel::LateralG.value(packet),
+        Channel::LongitudinalG.value(packet),
+    )
+}
+
+fn to_values(points: &[(f32, f32)]) -> Values {
+    Values::from_values_iter(points.iter().map(|(x, y)| Value::new(*x, *y)))
+}
+
+impl GgPanel {
+    fn update_layers(&mut self, layers: &[(String, &[forza::Packet])]) {
+        let key: Vec<_> = layers
+            .iter()
+            .map(|(name, packets)| (name.clone(), packets.len(), self.percentile))
+            .collect();
+        if key == self.layers_key {
+            return;
+        }
+
+        self.layers_key = key;
+        self.layers = layers
+            .iter()
+            .map(|(name, packets)| {
+                let points: Vec<_> = packets.iter().map(g_forces).collect();
+                Layer {
+                    name: name.clone(),
+                    envelope: forza::envelope(&points, self.percentile),
+                    points,
+                }
+            })
+            .collect();
+    }
+
+    fn layer_color(index: usize) -> egui::Color32 {
+        match index {
+            0 => SELECTION_COLOR,
+            _ => overlay_color(index - 1),
+        }
+    }
+
+    // Groups the points of the layers by colour, to draw them in a few calls
+    fn colored_points(
+        &self,
+        layers: &[(String, &[forza::Packet])],
+    ) -> Vec<(egui::Color32, Vec<Value>)> {
+        let max_speed = layers
+            .iter()
+            .flat_map(|(_, packets)| packets.iter())
+            .map(|p| p.speed)
+            .fold(0.0, f32::max);
+
+        let mut groups: Vec<(egui::Color32, Vec<Value>)> = Vec::new();
+        for (index, (layer, (_, packets))) in self.layers.iter().zip(layers).enumerate() {
+            for ((x, y), packet) in layer.points.iter().zip(packets.iter()) {
+                let color = match self.coloring {
+                    Coloring::Speed => {
+                        let t = match max_speed > 0.0 {
+                            true => packet.speed / max_speed,
+                            false => 0.0,
+                        };
+                        let step = (t * (COLOR_STEPS - 1) as f32).round();
+                        ColorRamp::Heat.color(step / (COLOR_STEPS - 1) as f32)
+                    }
+                    Coloring::Gear => ColorRamp::Viridis.color(packet.gear as f32 / 10.0),
+                    Coloring::Layer => Self::layer_color(index),
+                };
+
+                let point = Value::new(*x, *y);
+                match groups.iter_mut().find(|(c, _)| *c == color) {
+                    Some((_, points)) => points.push(point),
+                    None => groups.push((color, vec![point])),
+                }
+            }
+        }
+        groups
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::CtxRef,
+        layers: &[(String, &[forza::Packet])],
+        hovered: Option<&forza::Packet>,
+    ) {
+        self.update_layers(layers);
+
+        egui::Window::new("G-G diagram").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Colour by");
+                egui::ComboBox::from_id_source("G-G coloring")
+                    .selected_text(self.coloring.name())
+                    .show_ui(ui, |ui| {
+                        for coloring in Coloring::ALL {
+                            ui.selectable_value(&mut self.coloring, coloring, coloring.name());
+                        }
+                    });
+
+                ui.label("Envelope");
+                egui::ComboBox::from_id_source("G-G percentile")
+                    .selected_text(format!("{:.0}%", self.percentile * 100.0))
+                    .width(60.0)
+                    .show_ui(ui, |ui| {
+                        for percentile in PERCENTILES {
+                            let text = format!("{:.0}%", percentile * 100.0);
+                            ui.selectable_value(&mut self.percentile, percentile, text);
+                        }
+                    });
+            });
+
+            if self.layers.iter().all(|layer| layer.points.is_empty()) {
+                ui.label("No packet in the selection");
+                return;
+            }
+
+            let points = self.colored_points(layers);
+            plot::Plot::new("G-G diagram")
+                .data_aspect(1.0)
+                .include_x(-1.0)
+                .include_x(1.0)
+                .include_y(-1.0)
+                .include_y(1.0)
+                .legend(plot::Legend::default())
+                .show(ui, |plot_ui| {
+                    for (color, points) in points {
+                        plot_ui.points(
+                            plot::Points::new(Values::from_values(points))
+                                .color(color)
+                                .radius(1.5),
+                        );
+                    }
+
+                    // A closed line around the points reached in each direction
+                    for (index, layer) in self.layers.iter().enumerate() {
+                        let mut envelope = layer.envelope.clone();
+                        if let Some(first) = envelope.first().cloned() {
+                            envelope.push(first);
+                        }
+                        plot_ui.line(
+                            plot::Line::new(to_values(&envelope))
+                                .color(Self::layer_color(index))
+                                .width(2.0)
+                                .name(&layer.name),
+                        );
+                    }
+
+                    if let Some(packet) = hovered {
+                        let (x, y) = g_forces(packet);
+                        plot_ui.points(
+                            plot::Points::new(Values::from_values(vec![Value::new(x, y)]))
+                                .color(egui::Color32::WHITE)
+                                .shape(plot::MarkerShape::Circle)
+                                .filled(false)
+                                .radius(6.0),
+                        );
+                    }
+                });
+        });
+    }
+}
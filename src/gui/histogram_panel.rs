@@ -0,0 +1,200 @@
+use crate::forza::{self, Channel, ChunkSelector, Distribution, Wheel};
+use eframe::egui;
+
+use egui::plot::{self, Value, Values};
+
+const PERCENTILES: [f32; 3] = [0.05, 0.5, 0.95];
+const CHANNEL_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 48, 134);
+const WHEEL_COLORS: [egui::Color32; 4] = [
+    egui::Color32::from_rgb(255, 48, 134),
+    egui::Color32::from_rgb(80, 200, 255),
+    egui::Color32::from_rgb(240, 210, 80),
+    egui::Color32::from_rgb(80, 220, 100),
+];
+
+// The distribution of a channel, or of one wheel
+struct Series {
+    name: &'static str,
+    color: egui::Color32,
+    distribution: Distribution,
+}
+
+pub struct HistogramPanel {
+    channel: Channel,
+    gear: Option<u8>, // Only count the packets in this gear
+    bins: usize,
+    series: Vec<Series>,
+    gears: Vec<u8>, // Gears used in the selection
+    series_key: Option<(ChunkSelector, usize, Channel, Option<u8>)>,
+}
+
+impl Default for HistogramPanel {
+    fn default() -> Self {
+        Self {
+            channel: Channel::Speed,
+            gear: None,
+            bins: 40,
+            series: Vec::new(),
+            gears: Vec::new(),
+            series_key: None,
+        }
+    }
+}
+
+impl HistogramPanel {
+    fn update_series(&mut self, selection: ChunkSelector, packets: &[forza::Packet]) {
+        let key = Some((selection, packets.len(), self.channel, self.gear));
+        if key == self.series_key {
+            return;
+        }
+        self.series_key = key;
+
+        self.gears = packets.iter().map(|p| p.gear).collect();
+        self.gears.sort_unstable();
+        self.gears.dedup();
+
+        let gear = self.gear;
+        let packets = packets
+            .iter()
+            .filter(|p| gear.is_none_or(|gear| p.gear == gear));
+        let channel = self.channel;
+        self.series = match channel.is_per_wheel() {
+            true => Wheel::ALL
+                .iter()
+                .zip(WHEEL_COLORS)
+                .map(|(wheel, color)| Series {
+                    name: wheel.name(),
+                    color,
+                    distribution: Distribution::new(
+                        packets
+                            .clone()
+                            .filter_map(|p| channel.wheel_value(p, *wheel))
+                            .collect(),
+                    ),
+                })
+                .collect(),
+            false => vec![Series {
+                name: channel.name(),
+                color: CHANNEL_COLOR,
+                distribution: Distribution::new(packets.map(|p| channel.value(p)).collect()),
+            }],
+        };
+    }
+
+    fn show_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("Histogram channel")
+                .selected_text(self.channel.name())
+                .show_ui(ui, |ui| {
+                    for channel in Channel::ALL {
+                        ui.selectable_value(&mut self.channel, channel, channel.name());
+                    }
+                });
+
+            let gear_name = |gear: Option<u8>| match gear {
+                Some(0) => "Reverse".to_owned(),
+                Some(gear) => format!("Gear {}", gear),
+                None => "All gears".to_owned(),
+            };
+            egui::ComboBox::from_id_source("Histogram gear")
+                .selected_text(gear_name(self.gear))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.gear, None, gear_name(None));
+                    for gear in &self.gears {
+                        ui.selectable_value(&mut self.gear, Some(*gear), gear_name(Some(*gear)));
+                    }
+                });
+        });
+        ui.add(egui::Slider::new(&mut self.bins, 5..=100).text("Bins"));
+    }
+
+    fn show_statistics(&self, ui: &mut egui::Ui) {
+        let unit = self.channel.unit();
+        egui::Grid::new("Histogram statistics")
+            .striped(true)
+            .show(ui, |ui| {
+                for title in ["", "Min", "Mean", "Max", "P5", "P50", "P95"] {
+                    ui.label(title);
+                }
+                ui.end_row();
+
+                for series in &self.series {
+                    let distribution = &series.distribution;
+                    ui.colored_label(series.color, series.name);
+                    let values = [distribution.min(), distribution.mean(), distribution.max()]
+                        .into_iter()
+                        .chain(PERCENTILES.iter().map(|p| distribution.percentile(*p)));
+                    for value in values {
+                        ui.label(format!("{:.2} {}", value, unit));
+                    }
+                    ui.end_row();
+                }
+            });
+    }
+
+    pub fn show(&mut self, ctx: &egui::CtxRef, chunks: &forza::Chunks, selection: ChunkSelector) {
+        egui::Window::new("Histogram").show(ctx, |ui| {
+            self.show_settings(ui);
+            self.update_series(selection, chunks.packets_of(selection));
+            ui.separator();
+
+            let series: Vec<_> = self
+                .series
+                .iter()
+                .filter(|s| !s.distribution.is_empty())
+                .collect();
+            if series.is_empty() {
+                ui.label("No packet in the selection");
+                return;
+            }
+
+            let range = (
+                series
+                    .iter()
+                    .map(|s| s.distribution.min())
+                    .fold(f32::MAX, f32::min),
+                series
+                    .iter()
+                    .map(|s| s.distribution.max())
+                    .fold(f32::MIN, f32::max),
+            );
+            let width = (range.1 - range.0) / self.bins as f32;
+
+            // Bars as a filled step line, in percent of the packets
+            let lines = series.iter().map(|s| {
+                let total = s.distribution.len() as f32;
+                let counts = s.distribution.histogram(range, self.bins);
+                let mut values = vec![Value::new(range.0, 0.0)];
+                for (bin, count) in counts.iter().enumerate() {
+                    let height = *count as f32 / total * 100.0;
+                    let left = range.0 + bin as f32 * width;
+                    values.push(Value::new(left, height));
+                    values.push(Value::new(left + width, height));
+                }
+                values.push(Value::new(range.1, 0.0));
+
+                plot::Line::new(Values::from_values(values))
+                    .color(s.color)
+                    .fill(0.0)
+                    .name(s.name)
+            });
+            let lines: Vec<_> = lines.collect();
+
+            ui.label(format!(
+                "% of the packets by {} {}",
+                self.channel.name(),
+                self.channel.unit()
+            ));
+            plot::Plot::new("Histogram")
+                .height(200.0)
+                .include_y(0.0)
+                .legend(plot::Legend::default())
+                .show(ui, |plot_ui| {
+                    lines.into_iter().for_each(|line| plot_ui.line(line));
+                });
+
+            ui.separator();
+            self.show_statistics(ui);
+        });
+    }
+}
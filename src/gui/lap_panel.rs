@@ -0,0 +1,236 @@
+use crate::event::{self, EventGenerator};
+use crate::forza::{self, ChunkId, ChunkSelector, LapSummary};
+use crate::gui::format_time;
+use eframe::egui;
+
+pub enum EventTypes {
+    SelectLap(ChunkSelector),
+}
+type Events = event::Events<EventTypes>;
+
+#[derive(PartialEq, Clone, Copy)]
+enum Column {
+    Lap,
+    Time,
+    TopSpeed,
+    MinSpeed,
+    AverageSpeed,
+    MaxG,
+    FullThrottle,
+    Braking,
+    FuelUsed,
+    GearChanges,
+}
+
+impl Column {
+    const ALL: [Column; 10] = [
+        Column::Lap,
+        Column::Time,
+        Column::TopSpeed,
+        Column::MinSpeed,
+        Column::AverageSpeed,
+        Column::MaxG,
+        Column::FullThrottle,
+        Column::Braking,
+        Column::FuelUsed,
+        Column::GearChanges,
+    ];
+
+    fn title(&self) -> &'static str {
+        match self {
+            Column::Lap => "Lap",
+            Column::Time => "Time",
+            Column::TopSpeed => "Top km/h",
+            Column::MinSpeed => "Min km/h",
+            Column::AverageSpeed => "Avg km/h",
+            Column::MaxG => "Max g",
+            Column::FullThrottle => "Full throttle",
+            Column::Braking => "Braking",
+            Column::FuelUsed => "Fuel",
+            Column::GearChanges => "Shifts",
+        }
+    }
+
+    fn value(&self, summary: &LapSummary) -> f32 {
+        match self {
+            Column::Lap => summary.lap_num as f32,
+            Column::Time => summary.time(),
+            Column::TopSpeed => summary.top_speed,
+            Column::MinSpeed => summary.min_speed,
+            Column::AverageSpeed => summary.average_speed,
+            Column::MaxG => summary.max_g,
+            Column::FullThrottle => summary.full_throttle,
+            Column::Braking => summary.braking,
+            Column::FuelUsed => summary.fuel_used,
+            Column::GearChanges => summary.gear_changes as f32,
+        }
+    }
+
+    fn text(&self, summary: &LapSummary) -> String {
+        let value = self.value(summary);
+        match self {
+            Column::Lap => format!("{}", summary.lap_num + 1),
+            Column::Time => format_time(value),
+            Column::TopSpeed | Column::MinSpeed | Column::AverageSpeed => {
+                format!("{:.1}", value * 3.6)
+            }
+            Column::MaxG => format!("{:.2}", value),
+            Column::FullThrottle | Column::Braking => {
+                let time = summary.time();
+                match time > 0.0 {
+                    true => format!("{:.1} s ({:.0}%)", value, value / time * 100.0),
+                    false => format!("{:.1} s", value),
+                }
+            }
+            Column::FuelUsed => format!("{:.1}%", value * 100.0),
+            Column::GearChanges => format!("{}", value),
+        }
+    }
+}
+
+pub struct LapPanel {
+    summaries: Vec<LapSummary>,
+    summaries_key: Option<(ChunkId, usize, Option<forza::Gate>)>, // What the summaries describe
+    sort: (Column, bool),                                         // Column and ascending order
+    events: Events,
+}
+
+impl Default for LapPanel {
+    fn default() -> Self {
+        Self {
+            summaries: Vec::new(),
+            summaries_key: None,
+            sort: (Column::Lap, true),
+            events: Events::default(),
+        }
+    }
+}
+
+impl event::EventGenerator<EventTypes> for LapPanel {
+    fn events(&mut self) -> &mut Events {
+        &mut self.events
+    }
+}
+
+impl LapPanel {
+    fn update_summaries(&mut self, chunk_id: ChunkId, chunk: Option<&forza::Chunk>) {
+        let key = chunk.map(|chunk| (chunk_id, chunk.packets.len(), chunk.gate));
+        if key != self.summaries_key {
+            self.summaries_key = key;
+            self.summaries = chunk.map(|chunk| chunk.lap_summaries()).unwrap_or_default();
+            self.sort_summaries();
+        }
+    }
+
+    fn sort_summaries(&mut self) {
+        let (column, ascending) = self.sort;
+        self.summaries.sort_by(|a, b| {
+            let order = column.value(a).total_cmp(&column.value(b));
+            match ascending {
+                true => order,
+                false => order.reverse(),
+            }
+        });
+    }
+
+    fn sort_by(&mut self, column: Column) {
+        self.sort = match self.sort {
+            (sorted, ascending) if sorted == column => (column, !ascending),
+            _ => (column, true),
+        };
+        self.sort_summaries();
+    }
+
+    pub fn show(&mut self, ctx: &egui::CtxRef, chunks: &forza::Chunks, selection: ChunkSelector) {
+        let ChunkSelector(chunk_id, lap_id) = selection;
+        self.update_summaries(chunk_id, chunks.get(chunk_id));
+
+        // Only the laps timed by the game, or by the start/finish line, can be the best
+        let best_lap = self
+            .summaries
+            .iter()
+            .filter_map(|s| Some((s.lap_num, s.lap_time?)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(lap_num, _)| lap_num);
+
+        egui::Window::new("Laps").show(ctx, |ui| {
+            if self.summaries.is_empty() {
+                ui.label("No lap in the selection");
+                return;
+            }
+
+            egui::ScrollArea::both().show(ui, |ui| {
+                egui::Grid::new("Laps")
+                    .striped(true)
+                    .num_columns(Column::ALL.len())
+                    .show(ui, |ui| {
+                        for column in Column::ALL {
+                            let title = match self.sort {
+                                (sorted, true) if sorted == column => {
+                                    format!("{} ⏶", column.title())
+                                }
+                                (sorted, false) if sorted == column => {
+                                    format!("{} ⏷", column.title())
+                                }
+                                _ => column.title().to_owned(),
+                            };
+                            if ui.selectable_label(self.sort.0 == column, title).clicked() {
+                                self.sort_by(column);
+                            }
+                        }
+                        ui.end_row();
+
+                        let mut selected_lap = None;
+                        for summary in &self.summaries {
+                            let is_best = best_lap == Some(summary.lap_num);
+                            Self::show_row(ui, summary, lap_id, is_best, &mut selected_lap);
+                            ui.end_row();
+                        }
+
+                        if let Some(lap_num) = selected_lap {
+                            self.gen_event(EventTypes::SelectLap(ChunkSelector(
+                                chunk_id,
+                                Some(lap_num),
+                            )));
+                        }
+                    });
+            });
+        });
+    }
+
+    fn show_row(
+        ui: &mut egui::Ui,
+        summary: &LapSummary,
+        lap_id: forza::LapId,
+        is_best: bool,
+        selected_lap: &mut Option<u16>,
+    ) {
+        let is_selected = lap_id == Some(summary.lap_num);
+        let lap = ui.selectable_label(is_selected, Column::Lap.text(summary));
+        if lap.clicked() {
+            *selected_lap = Some(summary.lap_num);
+        }
+
+        let color = match (is_best, summary.lap_time.is_some()) {
+            (true, _) => egui::Color32::from_rgb(80, 220, 100),
+            (false, true) => ui.visuals().text_color(),
+            (false, false) => ui.visuals().weak_text_color(),
+        };
+
+        for column in &Column::ALL[1..] {
+            let label = ui.colored_label(color, column.text(summary));
+            match (column, summary.is_consistent()) {
+                (Column::Time, false) => {
+                    label.on_hover_text(format!(
+                        "⚠ The packets span {}, some may be missing",
+                        format_time(summary.elapsed)
+                    ));
+                }
+                (Column::Time, true) if summary.lap_time.is_none() => {
+                    label.on_hover_text("Unfinished lap, timed from the packets");
+                }
+                _ => {}
+            }
+        }
+    }
+}
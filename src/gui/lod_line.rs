@@ -0,0 +1,144 @@
+use eframe::egui;
+
+use egui::plot::{PlotBounds, Value};
+
+const LEVELS: usize = 12; // Tolerances from 0.25 m to 512 m, doubling at each level
+const FINEST_TOLERANCE: f64 = 0.25;
+const TOLERANCE_PIXELS: f64 = 0.5; // Simplification hidden by the screen resolution
+const MIN_REBUILD: usize = 2000; // Points appended before they are simplified
+
+// A polyline simplified with Douglas-Peucker at several tolerances, so the map
+// draws the detail the zoom can show, and only the part in view
+#[derive(Default)]
+pub struct LodLine {
+    points: Vec<Value>,
+    levels: Vec<Vec<usize>>, // Indices of the points kept at each tolerance
+    simplified: usize,       // Points covered by the levels, the others are kept at every level
+}
+
+fn distance_to_segment(p: Value, a: Value, b: Value) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let length_sq = dx * dx + dy * dy;
+    let t = match length_sq > 0.0 {
+        true => (((p.x - a.x) * dx + (p.y - a.y) * dy) / length_sq).clamp(0.0, 1.0),
+        false => 0.0,
+    };
+    (p.x - a.x - t * dx).hypot(p.y - a.y - t * dy)
+}
+
+fn tolerance(level: usize) -> f64 {
+    FINEST_TOLERANCE * 2f64.powi(level as i32)
+}
+
+impl LodLine {
+    pub fn new(points: Vec<Value>) -> Self {
+        // The tolerance under which each point is needed. A point is never kept
+        // longer than the one that split its segment, so the levels are nested
+        let len = points.len();
+        let mut importance = vec![f64::INFINITY; len];
+        let mut segments = Vec::new();
+        if len > 2 {
+            segments.push((0, len - 1, f64::INFINITY));
+        }
+        while let Some((first, last, parent)) = segments.pop() {
+            let farthest = (first + 1..last)
+                .map(|i| {
+                    (
+                        i,
+                        distance_to_segment(points[i], points[first], points[last]),
+                    )
+                })
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((index, distance)) = farthest {
+                importance[index] = distance.min(parent);
+                segments.push((first, index, importance[index]));
+                segments.push((index, last, importance[index]));
+            }
+        }
+
+        let levels = (0..LEVELS)
+            .map(|level| {
+                let tolerance = tolerance(level);
+                (0..len).filter(|i| importance[*i] > tolerance).collect()
+            })
+            .collect();
+        Self {
+            simplified: len,
+            levels,
+            points,
+        }
+    }
+
+    // The last simplified point is kept at every level, so appended points are
+    // drawn as they are until there are enough of them to simplify again
+    pub fn extend(&mut self, points: impl IntoIterator<Item = Value>) {
+        for point in points {
+            let index = self.points.len();
+            self.points.push(point);
+            self.levels.iter_mut().for_each(|level| level.push(index));
+        }
+
+        let appended = self.points.len() - self.simplified;
+        if self.levels.is_empty() || appended > MIN_REBUILD.max(self.simplified / 4) {
+            *self = Self::new(std::mem::take(&mut self.points));
+        }
+    }
+
+    pub fn points(&self) -> &[Value] {
+        &self.points
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    // Runs of indices of the points to draw. A run ends where the line leaves the view
+    pub fn visible(&self, bounds: &PlotBounds, world_per_pixel: f64) -> Vec<Vec<usize>> {
+        let wanted = world_per_pixel * TOLERANCE_PIXELS;
+        let level = (0..LEVELS)
+            .take_while(|level| tolerance(*level) <= wanted)
+            .last();
+        let all_points: Vec<usize>;
+        let indices = match level {
+            Some(level) => &self.levels[level],
+            None => {
+                all_points = (0..self.points.len()).collect();
+                &all_points
+            }
+        };
+
+        let (min, max) = (bounds.min(), bounds.max());
+        let margin = (max[0] - min[0]).max(max[1] - min[1]) * 0.1;
+        let inside = |p: Value| {
+            (min[0] - margin..=max[0] + margin).contains(&p.x)
+                && (min[1] - margin..=max[1] + margin).contains(&p.y)
+        };
+
+        // The points just outside are kept, so the line reaches the border
+        let mut runs = Vec::new();
+        let mut run: Vec<usize> = Vec::new();
+        for (i, index) in indices.iter().enumerate() {
+            let visible = [i.checked_sub(1), Some(i), Some(i + 1)]
+                .iter()
+                .flatten()
+                .filter_map(|i| indices.get(*i))
+                .any(|index| inside(self.points[*index]));
+            match visible {
+                true => run.push(*index),
+                false if !run.is_empty() => runs.push(std::mem::take(&mut run)),
+                false => {}
+            }
+        }
+        if !run.is_empty() {
+            runs.push(run);
+        }
+        runs
+    }
+
+    pub fn visible_points(&self, bounds: &PlotBounds, world_per_pixel: f64) -> Vec<Vec<Value>> {
+        self.visible(bounds, world_per_pixel)
+            .into_iter()
+            .map(|run| run.into_iter().map(|i| self.points[i]).collect())
+            .collect()
+    }
+}
@@ -1,23 +1,87 @@
-use std::mem::replace;
 use std::ops::Mul;
 
+use crate::dialog;
+use crate::event::{self, EventGenerator};
 use crate::forza;
+use crate::gui::{compare_panel, pin_color, ColorRamp, LodLine, MapTrace, Pin};
 use eframe::egui;
 
-use eframe::egui::epaint::util::FloatOrd;
 use egui::plot;
 use egui::plot::{PlotImage, Value, Values};
 use egui::{TextureId, Vec2};
 
+const COLOR_STEPS: usize = 16; // Distinct colours of a coloured trace
+const PICK_RADIUS: f32 = 12.0; // Pixels around the pointer where a packet is hovered
+
+const OVERLAY_COLORS: [egui::Color32; 6] = [
+    egui::Color32::from_rgb(240, 210, 80),
+    egui::Color32::from_rgb(80, 220, 100),
+    egui::Color32::from_rgb(180, 100, 255),
+    egui::Color32::from_rgb(255, 140, 40),
+    egui::Color32::from_rgb(40, 120, 255),
+    egui::Color32::from_rgb(230, 230, 230),
+];
+
+pub fn overlay_color(index: usize) -> egui::Color32 {
+    OVERLAY_COLORS[index % OVERLAY_COLORS.len()]
+}
+
+pub enum EventTypes {
+    SetGate(Option<forza::Gate>),
+    AddSector(forza::Gate),
+    ChangeColoring,
+    SetGame(forza::Game),
+    SelectMap(usize),
+    LoadMap(String),
+    RemoveMap(usize),
+    Calibrate(Vec<forza::CalibrationPoint>),
+    SelectPackets(std::ops::Range<usize>),
+    PinPacket(usize, usize), // Layer and index of the packet
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum GateKind {
+    Start,
+    Sector,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum AreaShape {
+    Rectangle,
+    Lasso,
+}
+type Events = event::Events<EventTypes>;
+
 pub struct MapPanel {
     pointer_coord: Option<Value>,
     image: TextureId,
     image_pos: Value,
     image_size: Vec2,
     scale: f32,
-    max_len: usize,
-    tracks: Vec<Vec<Value>>,
-    tracks_step_by: usize,
+    map: usize,
+    auto_map: bool,
+    calibrating: Option<Vec<(Value, Option<Value>)>>, // Points on the image, then on the trace
+    fit_error: Option<(usize, f32)>,                  // Of the last calibrated map, in meters
+    selection: MapTrace,
+    coloring: Option<forza::Channel>,
+    ramp: ColorRamp,
+    auto_range: bool,
+    range: (f32, f32),
+    overlays: Vec<(String, MapTrace)>,
+    world_per_pixel: f32, // Of the last frame, to pick packets in pixels
+    hover_label: Option<String>,
+    marker: Option<Value>,       // Packet hovered elsewhere, like in the plots
+    car: Option<(Value, Value)>, // Position and heading of the played back car
+    rewinds: Vec<Value>,
+    pins: Vec<Value>,
+    highlight: MapTrace,
+    reference: MapTrace,
+    gate: Option<forza::Gate>,
+    sectors: Option<Vec<forza::Gate>>, // None without an active track
+    outline: Option<forza::Outline>,   // Replaces the image when it does not cover the selection
+    placing_gate: Option<(GateKind, Vec<Value>)>,
+    selecting_area: Option<(AreaShape, Vec<Value>)>,
+    events: Events,
 }
 
 impl Default for MapPanel {
@@ -31,57 +95,604 @@ impl Default for MapPanel {
             },
             image_size: [5615.0, 3245.0].into(),
             scale: 3.475,
-            max_len: 6000,
-            tracks: Vec::default(),
-            tracks_step_by: 1,
+            map: 0,
+            auto_map: true,
+            calibrating: None,
+            fit_error: None,
+            selection: MapTrace::default(),
+            coloring: None,
+            ramp: ColorRamp::default(),
+            auto_range: true,
+            range: (0.0, 1.0),
+            overlays: Vec::default(),
+            world_per_pixel: 1.0,
+            hover_label: None,
+            marker: None,
+            car: None,
+            rewinds: Vec::default(),
+            pins: Vec::default(),
+            highlight: MapTrace::default(),
+            reference: MapTrace::default(),
+            gate: None,
+            sectors: None,
+            outline: None,
+            placing_gate: None,
+            selecting_area: None,
+            events: Events::default(),
         }
     }
 }
 
+impl event::EventGenerator<EventTypes> for MapPanel {
+    fn events(&mut self) -> &mut Events {
+        &mut self.events
+    }
+}
+
 impl MapPanel {
     pub fn set_image(&mut self, size: Vec2, image: TextureId) {
         self.image_size = size;
         self.image = image;
     }
 
+    pub fn set_map(&mut self, index: usize, profile: &forza::MapProfile) {
+        self.map = index;
+        self.image_pos = Value::new(profile.center.0, profile.center.1);
+        self.scale = profile.scale;
+    }
+
+    pub fn set_fit_error(&mut self, index: usize, fit_error: f32) {
+        self.fit_error = Some((index, fit_error));
+    }
+
+    pub fn map(&self) -> usize {
+        self.map
+    }
+
+    // Follow the game of the selection
+    pub fn auto_map(&self) -> bool {
+        self.auto_map
+    }
+
     pub fn set_packets(&mut self, packets: &[forza::Packet]) {
-        self.tracks_step_by = 1 + packets.len() / self.max_len;
-        let mut last_distance = f32::NEG_INFINITY;
-        let mut current_line = Vec::new();
-        let mut lines = Vec::with_capacity(32);
-        for p in packets.iter().step_by(self.tracks_step_by) {
-            if p.distance_traveled < replace(&mut last_distance, p.distance_traveled) {
-                lines.push(replace(&mut current_line, Vec::new()));
-            }
+        if self.selection.update(packets, self.coloring) && self.auto_range {
+            self.fit_range();
+        }
+    }
 
+    fn fit_range(&mut self) {
+        let values = self.selection.values().iter().flatten();
+        let range = values.fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), v| {
+            (min.min(*v), max.max(*v))
+        });
+        // Without values, keep a range the legend and the drag values can show
+        self.range = match range.0 <= range.1 {
+            true => range,
+            false => (0.0, 1.0),
+        };
+    }
+
+    // The other laps or chunks drawn with their own colour
+    pub fn set_overlays(&mut self, overlays: &[(String, &[forza::Packet])]) {
+        self.overlays
+            .resize_with(overlays.len(), || (String::new(), MapTrace::default()));
+        for ((name, trace), (new_name, packets)) in self.overlays.iter_mut().zip(overlays) {
+            name.clone_from(new_name);
+            trace.update(packets, None);
+        }
+    }
+
+    pub fn set_marker(&mut self, packet: Option<&forza::Packet>) {
+        self.marker = packet.map(|p| Value::new(p.position().0, p.position().1));
+    }
+
+    pub fn set_car(&mut self, packet: Option<&forza::Packet>) {
+        self.car = packet.map(|p| {
             let (x, y) = p.position();
-            current_line.push(Value::new(x, y));
+            let yaw = p.rotation.x;
+            (Value::new(x, y), Value::new(yaw.sin(), yaw.cos()))
+        });
+    }
+
+    pub fn set_hover_label(&mut self, label: Option<String>) {
+        self.hover_label = label;
+    }
+
+    pub fn set_rewinds(&mut self, rewinds: &[&forza::Rewind]) {
+        self.rewinds = rewinds
+            .iter()
+            .map(|r| Value::new(r.position.0, r.position.1))
+            .collect();
+    }
+
+    pub fn set_pins(&mut self, pins: &[Pin]) {
+        self.pins = pins
+            .iter()
+            .map(|pin| Value::new(pin.packet.position().0, pin.packet.position().1))
+            .collect();
+    }
+
+    // Set at every frame, but only traced again when the packets change
+    pub fn set_highlight(&mut self, packets: &[forza::Packet]) {
+        self.highlight.update(packets, None);
+    }
+
+    // The lap the selection is compared with
+    pub fn set_reference(&mut self, packets: &[forza::Packet]) {
+        self.reference.update(packets, None);
+    }
+
+    pub fn set_gate(&mut self, gate: Option<forza::Gate>) {
+        self.gate = gate;
+    }
+
+    pub fn set_sectors(&mut self, sectors: Option<&[forza::Gate]>) {
+        self.sectors = sectors.map(|s| s.to_vec());
+    }
+
+    pub fn set_outline(&mut self, outline: Option<forza::Outline>) {
+        self.outline = outline;
+    }
+
+    fn draw_outline(plot_ui: &mut plot::PlotUi, outline: &forza::Outline) {
+        let to_value = |(x, z): (f32, f32)| Value::new(x, z);
+        let asphalt = egui::Color32::from_gray(70);
+        let edge = egui::Color32::from_gray(200);
+
+        // The asphalt is drawn piece by piece, as the whole track is not convex
+        let len = outline.center.len();
+        let pieces = match outline.closed {
+            true => len,
+            false => len - 1,
+        };
+        for i in 0..pieces {
+            let next = (i + 1) % len;
+            let piece = [
+                outline.left[i],
+                outline.left[next],
+                outline.right[next],
+                outline.right[i],
+            ];
+            plot_ui.polygon(
+                plot::Polygon::new(Values::from_values_iter(piece.into_iter().map(to_value)))
+                    .color(asphalt)
+                    .fill_alpha(1.0)
+                    .width(0.0),
+            );
+        }
+
+        for side in [&outline.left, &outline.right] {
+            let mut points: Vec<_> = side.iter().cloned().map(to_value).collect();
+            if outline.closed {
+                points.extend(points.first().cloned());
+            }
+            plot_ui.line(
+                plot::Line::new(Values::from_values(points))
+                    .color(edge)
+                    .width(1.5),
+            );
+        }
+
+        if let Some(forza::Gate(a, b)) = outline.start_line() {
+            plot_ui.line(
+                plot::Line::new(Values::from_values(vec![to_value(a), to_value(b)]))
+                    .color(egui::Color32::WHITE)
+                    .width(3.0),
+            );
+        }
+        if let Some((start, ahead)) = outline.direction() {
+            plot_ui.arrows(
+                plot::Arrows::new(
+                    Values::from_values(vec![to_value(start)]),
+                    Values::from_values(vec![to_value(ahead)]),
+                )
+                .color(egui::Color32::WHITE),
+            );
+        }
+    }
+
+    fn show_coloring_tools(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let mut coloring = self.coloring;
+            let selected_text = coloring.map_or("Plain", |channel| channel.name());
+            egui::ComboBox::from_label("Colour by")
+                .selected_text(selected_text)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut coloring, None, "Plain");
+                    for channel in forza::Channel::ALL {
+                        ui.selectable_value(&mut coloring, Some(channel), channel.name());
+                    }
+                });
+            if coloring != self.coloring {
+                self.coloring = coloring;
+                self.gen_event(EventTypes::ChangeColoring);
+            }
+            if self.coloring.is_none() {
+                return;
+            }
+
+            egui::ComboBox::from_id_source("Ramp")
+                .selected_text(self.ramp.name())
+                .show_ui(ui, |ui| {
+                    for ramp in ColorRamp::ALL {
+                        ui.selectable_value(&mut self.ramp, ramp, ramp.name());
+                    }
+                });
+
+            if ui.checkbox(&mut self.auto_range, "Auto").changed() && self.auto_range {
+                self.fit_range();
+            }
+            ui.add_enabled_ui(!self.auto_range, |ui| {
+                let speed = ((self.range.1 - self.range.0).abs() / 100.0).max(0.01);
+                ui.add(egui::DragValue::new(&mut self.range.0).speed(speed));
+                ui.add(egui::DragValue::new(&mut self.range.1).speed(speed));
+            });
+        });
+
+        if let Some(channel) = self.coloring {
+            self.show_color_legend(ui, channel);
         }
-        lines.push(current_line);
+    }
 
-        self.tracks = lines;
+    fn show_color_legend(&self, ui: &mut egui::Ui, channel: forza::Channel) {
+        let available_space = ui.available_size_before_wrap();
+        let size = egui::vec2(available_space.x.min(400.0), 14.0);
+        let (rect, _) = ui.allocate_at_least(size, egui::Sense::hover());
+
+        let painter = ui.painter();
+        let step_width = rect.width() / COLOR_STEPS as f32;
+        for step in 0..COLOR_STEPS {
+            let min = rect.min + egui::vec2(step as f32 * step_width, 0.0);
+            let step_rect = egui::Rect::from_min_size(min, egui::vec2(step_width, rect.height()));
+            let t = step as f32 / (COLOR_STEPS - 1) as f32;
+            painter.rect_filled(step_rect, 0.0, self.ramp.color(t));
+        }
+
+        let (min, max) = self.range;
+        let texts = [
+            (
+                egui::Align2::LEFT_CENTER,
+                rect.left_center(),
+                format!("{:.1}", min),
+                0.0,
+            ),
+            (
+                egui::Align2::CENTER_CENTER,
+                rect.center(),
+                format!("{} {}", channel.name(), channel.unit()),
+                0.5,
+            ),
+            (
+                egui::Align2::RIGHT_CENTER,
+                rect.right_center(),
+                format!("{:.1}", max),
+                1.0,
+            ),
+        ];
+        for (align, pos, text, t) in texts {
+            painter.text(
+                pos,
+                align,
+                text,
+                egui::TextStyle::Small,
+                self.ramp.text_color(t),
+            );
+        }
     }
 
-    pub fn hovered_packet<'a>(&self, packets: &'a [forza::Packet]) -> Option<&'a forza::Packet> {
-        let dist = |mpos: Value, p: &forza::Packet| {
-            egui::Pos2::from(p.position()).distance_sq(mpos.to_pos2())
+    // Group the consecutive points of the same colour into lines
+    fn colored_lines(&self, track: &[Value], values: &[f32]) -> Vec<(egui::Color32, Vec<Value>)> {
+        let (min, max) = self.range;
+        let step_of = |value: f32| {
+            let t = match max > min {
+                true => ((value - min) / (max - min)).clamp(0.0, 1.0),
+                false => 0.0,
+            };
+            (t * (COLOR_STEPS - 1) as f32).round() as usize
         };
 
-        self.pointer_coord.and_then(|mpos| {
-            packets
-                .iter()
-                .min_by_key(|p| dist(mpos, p).ord())
-                .filter(|p| dist(mpos, p) <= (16.0f32).powi(2))
-        })
+        let mut lines: Vec<(usize, Vec<Value>)> = Vec::new();
+        for (point, value) in track.iter().zip(values) {
+            let step = step_of(*value);
+            match lines.last_mut() {
+                Some((last_step, line)) if *last_step == step => line.push(*point),
+                Some((_, line)) => {
+                    // Start from the last point so the line stays continuous
+                    let last_point = *line.last().unwrap();
+                    line.push(*point);
+                    lines.push((step, vec![last_point, *point]));
+                }
+                None => lines.push((step, vec![*point])),
+            }
+        }
+
+        lines
+            .into_iter()
+            .map(|(step, line)| {
+                let color = self.ramp.color(step as f32 / (COLOR_STEPS - 1) as f32);
+                (color, line)
+            })
+            .collect()
     }
 
-    pub fn show(&mut self, ctx: &egui::CtxRef) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            // ui.add(egui::Slider::new(&mut self.image_pos.x, -2000.0..=-1900.0));
-            // ui.add(egui::Slider::new(&mut self.image_pos.y, 400.0..=500.0));
-            // ui.add(egui::Slider::new(&mut self.scale, 5.8..=6.2));
-            // ui.add(egui::Slider::new(&mut self.len, 0..=10000));
+    fn show_gate_tools(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| match &self.placing_gate {
+            Some((kind, points)) => {
+                let line = match kind {
+                    GateKind::Start => "start/finish line",
+                    GateKind::Sector => "sector line",
+                };
+                ui.label(format!(
+                    "Click both ends of the {} ({}/2)",
+                    line,
+                    points.len()
+                ));
+                if ui.button("Cancel").clicked() {
+                    self.placing_gate = None;
+                }
+            }
+            None => {
+                if ui
+                    .button("🏁 Place start/finish")
+                    .on_hover_text("Cut free roam chunks into laps at this line")
+                    .clicked()
+                {
+                    self.calibrating = None;
+                    self.placing_gate = Some((GateKind::Start, Vec::with_capacity(2)));
+                }
+                if self.gate.is_some() && ui.button("Clear start/finish").clicked() {
+                    self.gen_event(EventTypes::SetGate(None));
+                }
+                if ui
+                    .add_enabled(self.sectors.is_some(), egui::Button::new("Place sector"))
+                    .on_hover_text("Add a sector line to the active track, in driving order")
+                    .clicked()
+                {
+                    self.calibrating = None;
+                    self.placing_gate = Some((GateKind::Sector, Vec::with_capacity(2)));
+                }
+            }
+        });
+    }
+
+    fn show_map_tools(&mut self, ui: &mut egui::Ui, maps: &forza::MapProfiles) {
+        ui.horizontal(|ui| {
+            let mut game = maps.game();
+            egui::ComboBox::from_label("Game")
+                .selected_text(game.name())
+                .show_ui(ui, |ui| {
+                    for option in forza::Game::ALL {
+                        ui.selectable_value(&mut game, option, option.name());
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "Forza Horizon 4 and 5 send the same packets. With Auto, the game \
+                     follows the maps covering the selection, when they are all of one game",
+                );
+            if game != maps.game() {
+                self.gen_event(EventTypes::SetGame(game));
+            }
+
+            // Only the maps of the game can be picked
+            let mut map = self.map;
+            let selected_text = maps
+                .get(map)
+                .filter(|profile| profile.game == game)
+                .map_or("None", |profile| &profile.name);
+            egui::ComboBox::from_label("Map")
+                .selected_text(selected_text)
+                .show_ui(ui, |ui| {
+                    for (index, profile) in maps.iter().enumerate() {
+                        if profile.game == game {
+                            ui.selectable_value(&mut map, index, &profile.name);
+                        }
+                    }
+                });
+            if map != self.map {
+                self.auto_map = false;
+                self.gen_event(EventTypes::SelectMap(map));
+            }
+            ui.checkbox(&mut self.auto_map, "Auto")
+                .on_hover_text("Show the map where the selection was recorded");
+
+            if ui.button("Load image…").clicked() {
+                if let Some(path) = dialog::pick_image_dialog() {
+                    self.auto_map = false;
+                    self.gen_event(EventTypes::LoadMap(path));
+                }
+            }
+            let is_loaded = maps.get(self.map).is_some_and(|p| p.image.is_some());
+            if is_loaded && ui.button("Remove").clicked() {
+                self.gen_event(EventTypes::RemoveMap(self.map));
+            }
+
+            match &self.calibrating {
+                Some(points) => {
+                    let complete = points.iter().filter(|(_, world)| world.is_some()).count();
+                    match points.last() {
+                        Some((_, None)) => ui.label("Click the same place on the trace"),
+                        _ => ui.label(format!(
+                            "Click a landmark of the image ({}/3)",
+                            complete + 1
+                        )),
+                    };
+                    if ui
+                        .add_enabled(complete >= 2, egui::Button::new("Apply"))
+                        .clicked()
+                    {
+                        self.finish_calibration();
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.calibrating = None;
+                    }
+                }
+                None => {
+                    if ui
+                        .button("Calibrate")
+                        .on_hover_text("Align the image on two or three places of a trace")
+                        .clicked()
+                    {
+                        self.placing_gate = None;
+                        self.calibrating = Some(Vec::with_capacity(3));
+                        self.fit_error = None;
+                    }
+                    if let Some((_, fit_error)) = self.fit_error.filter(|(map, _)| *map == self.map)
+                    {
+                        ui.label(format!("± {:.0} m", fit_error)).on_hover_text(
+                            "Mean distance between the landmarks and where the image puts them",
+                        );
+                    }
+                }
+            }
+        });
+    }
+
+    fn place_calibration_point(&mut self, point: Value) {
+        if let Some(points) = &mut self.calibrating {
+            match points.last_mut() {
+                Some((_, world @ None)) => *world = Some(point),
+                _ => points.push((point, None)),
+            }
+            if points.iter().filter(|(_, world)| world.is_some()).count() == 3 {
+                self.finish_calibration();
+            }
+        }
+    }
+
+    fn finish_calibration(&mut self) {
+        let points = self.calibrating.take().unwrap_or_default();
+        let (center, scale) = (self.image_pos, self.scale as f64);
+        let points = points
+            .into_iter()
+            .filter_map(|(image, world)| {
+                let pixels = (
+                    ((image.x - center.x) / scale) as f32,
+                    ((image.y - center.y) / scale) as f32,
+                );
+                world.map(|world| (pixels, (world.x as f32, world.y as f32)))
+            })
+            .collect();
+        self.gen_event(EventTypes::Calibrate(points));
+    }
+
+    fn place_gate_point(&mut self, point: Value) {
+        if let Some((kind, points)) = &mut self.placing_gate {
+            points.push(point);
+            if let [a, b] = points[..] {
+                let gate = forza::Gate((a.x as f32, a.y as f32), (b.x as f32, b.y as f32));
+                match *kind {
+                    GateKind::Start => self.gen_event(EventTypes::SetGate(Some(gate))),
+                    GateKind::Sector => self.gen_event(EventTypes::AddSector(gate)),
+                }
+                self.placing_gate = None;
+            }
+        }
+    }
+
+    // The index of the nearest packet among the layers, and of its layer
+    pub fn hovered_packet(&self) -> Option<(usize, usize)> {
+        let mpos = self.pointer_coord?;
+        let position = (mpos.x as f32, mpos.y as f32);
+        let radius = PICK_RADIUS * self.world_per_pixel;
+
+        std::iter::once(&self.selection)
+            .chain(self.overlays.iter().map(|(_, trace)| trace))
+            .map(MapTrace::index)
+            .enumerate()
+            .filter_map(|(layer, index)| {
+                let (packet, distance) = index.nearest(position, radius)?;
+                Some((layer, packet, distance))
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(layer, packet, _)| (layer, packet))
+    }
+
+    fn show_area_tools(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| match self.selecting_area {
+            Some((shape, _)) => {
+                let shape = match shape {
+                    AreaShape::Rectangle => "a rectangle",
+                    AreaShape::Lasso => "a lasso",
+                };
+                ui.label(format!("Drag {} around the packets to edit", shape));
+                if ui.button("Cancel").clicked() {
+                    self.selecting_area = None;
+                }
+            }
+            None => {
+                let hover_text = "Set the edit range to the selected packets inside an area";
+                for (shape, label) in [
+                    (AreaShape::Rectangle, "⬜ Select area"),
+                    (AreaShape::Lasso, "Lasso"),
+                ] {
+                    if ui.button(label).on_hover_text(hover_text).clicked() {
+                        self.calibrating = None;
+                        self.placing_gate = None;
+                        self.selecting_area = Some((shape, Vec::new()));
+                    }
+                }
+            }
+        });
+    }
+
+    fn drag_area(&mut self, dragging: bool) {
+        let (shape, points) = match &mut self.selecting_area {
+            Some(area) => area,
+            None => return,
+        };
+        match (dragging, self.pointer_coord) {
+            (true, Some(coord)) => {
+                // A rectangle only keeps the corner where the drag started and the pointer
+                if *shape == AreaShape::Rectangle {
+                    points.truncate(1);
+                }
+                points.push(coord);
+            }
+            (false, _) if points.len() >= 2 => self.finish_area(),
+            _ => {}
+        }
+    }
+
+    fn area_polygon(shape: AreaShape, points: &[Value]) -> Vec<Value> {
+        match (shape, points) {
+            (AreaShape::Rectangle, [a, .., b]) => {
+                vec![*a, Value::new(b.x, a.y), *b, Value::new(a.x, b.y)]
+            }
+            _ => points.to_vec(),
+        }
+    }
+
+    // The longest pass through the area, as a lap can go by it several times
+    fn finish_area(&mut self) {
+        let (shape, points) = match self.selecting_area.take() {
+            Some(area) => area,
+            None => return,
+        };
+        let polygon: Vec<_> = Self::area_polygon(shape, &points)
+            .iter()
+            .map(|p| (p.x as f32, p.y as f32))
+            .collect();
+        let inside = self.selection.index().in_polygon(&polygon);
+
+        let mut longest = 0..0;
+        let mut start = 0;
+        for (i, index) in inside.iter().enumerate() {
+            if i > 0 && inside[i - 1] + 1 != *index {
+                start = i;
+            }
+            if i + 1 - start > longest.len() {
+                longest = start..i + 1;
+            }
+        }
+        if !longest.is_empty() {
+            let range = inside[longest.start]..inside[longest.end - 1] + 1;
+            self.gen_event(EventTypes::SelectPackets(range));
+        }
+    }
 
+    pub fn show(&mut self, ctx: &egui::CtxRef, maps: &forza::MapProfiles) {
+        egui::CentralPanel::default().show(ctx, |ui| {
             // let log = packets
             //     .iter()
             //     .filter(|p| !p.position_x.is_normal())
@@ -94,49 +705,235 @@ impl MapPanel {
             //     })
             // });
 
-            // The maximum number of points the plot can display is ~10k
-            // This step is used to take 1 sample ever `step` to cap the number of points.
-
-            if self.tracks_step_by > 1 {
-                let available_space = ui.available_size_before_wrap();
-                let size = egui::vec2(available_space.x, 12.0);
-                let (rect, _) = ui.allocate_at_least(size, egui::Sense::hover());
-
-                let painter = ui.painter();
+            self.show_map_tools(ui, maps);
+            self.show_coloring_tools(ui);
+            self.show_gate_tools(ui);
+            self.show_area_tools(ui);
 
-                painter.rect_filled(rect, 0.0, egui::Color32::from_rgb(250, 181, 127));
-                painter.text(
-                    rect.center(),
-                    egui::Align2::CENTER_CENTER,
-                    "Data are sampled on the map",
-                    egui::TextStyle::Small,
-                    egui::Color32::BLACK,
-                );
-            }
+            // The traces are simplified to what the pixels of the plot can show
+            let plot_width = ui.available_width().max(1.0) as f64;
 
             let image_plot =
                 PlotImage::new(self.image, self.image_pos, self.image_size.mul(self.scale));
+            let clicked = ui.input().pointer.any_click();
+            let dragging = ui.input().pointer.primary_down();
 
-            plot::Plot::new("Map").data_aspect(1.0).show(ui, |plot_ui| {
-                self.pointer_coord = match plot_ui.plot_hovered() {
-                    true => plot_ui.pointer_coordinate(),
-                    false => None,
-                };
+            plot::Plot::new("Map")
+                .data_aspect(1.0)
+                .legend(plot::Legend::default())
+                .allow_drag(self.selecting_area.is_none())
+                .show(ui, |plot_ui| {
+                    self.pointer_coord = match plot_ui.plot_hovered() {
+                        true => plot_ui.pointer_coordinate(),
+                        false => None,
+                    };
 
-                let line_color = egui::Color32::from_rgb(255, 48, 134);
-                let line_width = (4000.0 / plot_ui.plot_bounds().height() as f32).clamp(1.6, 16.0);
+                    self.drag_area(dragging);
+                    if let (true, Some(coord)) = (clicked, self.pointer_coord) {
+                        if self.calibrating.is_some() {
+                            self.place_calibration_point(coord);
+                        } else if self.placing_gate.is_some() {
+                            self.place_gate_point(coord);
+                        } else if self.selecting_area.is_none() {
+                            if let Some((layer, index)) = self.hovered_packet() {
+                                self.gen_event(EventTypes::PinPacket(layer, index));
+                            }
+                        }
+                    }
 
-                plot_ui.image(image_plot);
-                for track in self.tracks.to_owned() {
-                    let points = Values::from_values(track);
-                    plot_ui.line(plot::Line::new(points).color(line_color).width(line_width));
-                }
-            });
+                    let line_color = egui::Color32::from_rgb(255, 48, 134);
+                    let bounds = plot_ui.plot_bounds();
+                    let line_width = (4000.0 / bounds.height() as f32).clamp(1.6, 16.0);
+                    let world_per_pixel = bounds.width() / plot_width;
+                    self.world_per_pixel = world_per_pixel as f32;
+                    let visible = |line: &LodLine| line.visible_points(&bounds, world_per_pixel);
+
+                    match &self.outline {
+                        Some(outline) => Self::draw_outline(plot_ui, outline),
+                        None => plot_ui.image(image_plot),
+                    }
+                    for reference in self.reference.lines().iter().flat_map(visible) {
+                        plot_ui.line(
+                            plot::Line::new(Values::from_values(reference))
+                                .color(compare_panel::REFERENCE_COLOR)
+                                .width(line_width),
+                        );
+                    }
+                    if self.coloring.is_some() {
+                        let selection = &self.selection;
+                        for (track, values) in selection.lines().iter().zip(selection.values()) {
+                            for run in track.visible(&bounds, world_per_pixel) {
+                                let points: Vec<_> =
+                                    run.iter().map(|i| track.points()[*i]).collect();
+                                let values: Vec<_> = run.iter().map(|i| values[*i]).collect();
+                                for (color, line) in self.colored_lines(&points, &values) {
+                                    let points = Values::from_values(line);
+                                    plot_ui.line(
+                                        plot::Line::new(points).color(color).width(line_width),
+                                    );
+                                }
+                            }
+                        }
+                    } else {
+                        for track in self.selection.lines().iter().flat_map(visible) {
+                            let points = Values::from_values(track);
+                            plot_ui.line(
+                                plot::Line::new(points)
+                                    .color(line_color)
+                                    .width(line_width)
+                                    .name("Selection"),
+                            );
+                        }
+                    }
+
+                    for (index, (name, trace)) in self.overlays.iter().enumerate() {
+                        for line in trace.lines().iter().flat_map(visible) {
+                            let points = Values::from_values(line);
+                            plot_ui.line(
+                                plot::Line::new(points)
+                                    .color(overlay_color(index))
+                                    .width(line_width / 2.0)
+                                    .name(name),
+                            );
+                        }
+                    }
+
+                    for highlight in self.highlight.lines().iter().flat_map(visible) {
+                        plot_ui.line(
+                            plot::Line::new(Values::from_values(highlight))
+                                .color(egui::Color32::WHITE)
+                                .width(line_width / 2.0),
+                        );
+                    }
+
+                    if let Some(forza::Gate(a, b)) = self.gate {
+                        let gate =
+                            Values::from_values(vec![Value::new(a.0, a.1), Value::new(b.0, b.1)]);
+                        plot_ui.line(
+                            plot::Line::new(gate)
+                                .color(egui::Color32::WHITE)
+                                .width(line_width),
+                        );
+                    }
+
+                    for (sector_num, forza::Gate(a, b)) in self.sectors.iter().flatten().enumerate()
+                    {
+                        let sector =
+                            Values::from_values(vec![Value::new(a.0, a.1), Value::new(b.0, b.1)]);
+                        plot_ui.line(
+                            plot::Line::new(sector)
+                                .color(egui::Color32::from_rgb(240, 210, 80))
+                                .width(line_width / 2.0),
+                        );
+                        plot_ui.text(
+                            plot::Text::new(Value::new(a.0, a.1), format!("S{}", sector_num + 1))
+                                .color(egui::Color32::from_rgb(240, 210, 80)),
+                        );
+                    }
+
+                    if let Some((_, points)) = &self.placing_gate {
+                        let points = Values::from_values(points.to_owned());
+                        plot_ui.points(
+                            plot::Points::new(points)
+                                .color(egui::Color32::WHITE)
+                                .radius(4.0),
+                        );
+                    }
+
+                    if let Some((shape, points)) = &self.selecting_area {
+                        let area = Self::area_polygon(*shape, points);
+                        plot_ui.polygon(
+                            plot::Polygon::new(Values::from_values(area))
+                                .color(egui::Color32::WHITE)
+                                .fill_alpha(0.1)
+                                .width(1.0),
+                        );
+                    }
+
+                    // Each landmark of the image is linked to its place on the trace
+                    for (image, world) in self.calibrating.iter().flatten() {
+                        plot_ui.points(
+                            plot::Points::new(Values::from_values(vec![*image]))
+                                .shape(plot::MarkerShape::Cross)
+                                .radius(6.0)
+                                .color(egui::Color32::WHITE),
+                        );
+                        if let Some(world) = world {
+                            plot_ui.arrows(
+                                plot::Arrows::new(
+                                    Values::from_values(vec![*image]),
+                                    Values::from_values(vec![*world]),
+                                )
+                                .color(egui::Color32::from_rgb(240, 210, 80)),
+                            );
+                        }
+                    }
+
+                    if let Some(marker) = self.marker {
+                        plot_ui.points(
+                            plot::Points::new(Values::from_values(vec![marker]))
+                                .shape(plot::MarkerShape::Circle)
+                                .radius(6.0)
+                                .color(egui::Color32::WHITE),
+                        );
+                    }
+
+                    for (index, pin) in self.pins.iter().enumerate() {
+                        plot_ui.points(
+                            plot::Points::new(Values::from_values(vec![*pin]))
+                                .shape(plot::MarkerShape::Diamond)
+                                .radius(7.0)
+                                .color(pin_color(index)),
+                        );
+                        // The number above the marker, to find it in the packet panel
+                        let above = Value::new(pin.x, pin.y + 16.0 * world_per_pixel);
+                        plot_ui.text(
+                            plot::Text::new(above, format!("📌{}", index + 1))
+                                .color(pin_color(index)),
+                        );
+                    }
+
+                    if let Some((position, heading)) = self.car {
+                        // The arrow keeps the same length on screen
+                        let length = plot_ui.plot_bounds().height() / 25.0;
+                        let tip = Value::new(
+                            position.x + heading.x * length,
+                            position.y + heading.y * length,
+                        );
+                        let color = egui::Color32::from_rgb(80, 200, 255);
+                        plot_ui.arrows(
+                            plot::Arrows::new(
+                                Values::from_values(vec![position]),
+                                Values::from_values(vec![tip]),
+                            )
+                            .color(color),
+                        );
+                        plot_ui.points(
+                            plot::Points::new(Values::from_values(vec![position]))
+                                .radius(5.0)
+                                .color(color),
+                        );
+                    }
+
+                    if !self.rewinds.is_empty() {
+                        let rewinds = Values::from_values(self.rewinds.to_owned());
+                        plot_ui.points(
+                            plot::Points::new(rewinds)
+                                .shape(plot::MarkerShape::Left)
+                                .radius(6.0)
+                                .color(egui::Color32::from_rgb(250, 181, 127))
+                                .name("Rewind"),
+                        );
+                    }
+                });
         });
 
         egui::Window::new("mpos").show(ctx, |ui| {
             if let Some(pos) = self.pointer_coord {
                 ui.label(format!("x = {:.1}\ny = {:.1}", pos.x, pos.y));
+                if let Some(label) = &self.hover_label {
+                    ui.label(label);
+                }
             } else {
                 ui.label(format!("x = ø\ny = ø"));
             }
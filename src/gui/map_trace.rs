@@ -0,0 +1,85 @@
+use std::mem::replace;
+
+use crate::forza;
+use crate::gui::LodLine;
+use eframe::egui;
+
+use egui::plot::Value;
+
+// The path of some packets on the map. While recording, the packets only grow,
+// so only the new ones are added instead of simplifying everything again
+#[derive(Default)]
+pub struct MapTrace {
+    lines: Vec<LodLine>,   // Cut where the car was teleported back
+    values: Vec<Vec<f32>>, // Value of the coloring channel along the lines
+    coloring: Option<forza::Channel>,
+    index: forza::SpatialIndex,
+    len: usize,
+    ends: Option<(u32, u32)>, // Timestamps of the first and last packets
+    last_distance: f32,
+}
+
+fn ends(packets: &[forza::Packet]) -> Option<(u32, u32)> {
+    Some((packets.first()?.timestamp_ms, packets.last()?.timestamp_ms))
+}
+
+impl MapTrace {
+    pub fn lines(&self) -> &[LodLine] {
+        &self.lines
+    }
+
+    pub fn values(&self) -> &[Vec<f32>] {
+        &self.values
+    }
+
+    pub fn index(&self) -> &forza::SpatialIndex {
+        &self.index
+    }
+
+    // Returns if the trace changed
+    pub fn update(&mut self, packets: &[forza::Packet], coloring: Option<forza::Channel>) -> bool {
+        let unchanged_prefix = coloring == self.coloring
+            && packets.len() >= self.len
+            && ends(&packets[..self.len]) == self.ends;
+        if !unchanged_prefix {
+            *self = Self {
+                coloring,
+                ..Self::default()
+            };
+        }
+        if self.len == packets.len() && unchanged_prefix {
+            return false;
+        }
+
+        let new_packets = &packets[self.len..];
+        let mut start = 0;
+        for (i, p) in new_packets.iter().enumerate() {
+            let cut = p.distance_traveled < replace(&mut self.last_distance, p.distance_traveled);
+            if cut || self.lines.is_empty() {
+                self.extend_last(&new_packets[start..i]);
+                self.lines.push(LodLine::default());
+                self.values.extend(coloring.map(|_| Vec::new()));
+                start = i;
+            }
+        }
+        self.extend_last(&new_packets[start..]);
+
+        self.index.extend(new_packets.iter().map(|p| p.position()));
+        self.len = packets.len();
+        self.ends = ends(packets);
+        true
+    }
+
+    fn extend_last(&mut self, packets: &[forza::Packet]) {
+        if let Some(line) = self.lines.last_mut() {
+            line.extend(
+                packets
+                    .iter()
+                    .map(|p| Value::new(p.position().0, p.position().1)),
+            );
+        }
+        if let (Some(values), Some(channel)) = (self.values.last_mut(), self.coloring) {
+            values.extend(packets.iter().map(|p| channel.value(p)));
+        }
+    }
+}
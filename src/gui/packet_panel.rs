@@ -2,155 +2,348 @@ use eframe::egui;
 use egui::CtxRef;
 
 use crate::forza;
+use crate::gui::TireWidget;
+
+const MAX_PINS: usize = 6;
+const PIN_COLORS: [egui::Color32; MAX_PINS] = [
+    egui::Color32::from_rgb(80, 200, 255),
+    egui::Color32::from_rgb(240, 210, 80),
+    egui::Color32::from_rgb(80, 220, 100),
+    egui::Color32::from_rgb(180, 100, 255),
+    egui::Color32::from_rgb(255, 140, 40),
+    egui::Color32::from_rgb(230, 230, 230),
+];
+const DIFFERENCE_COLOR: egui::Color32 = egui::Color32::from_rgb(110, 60, 20);
+
+pub fn pin_color(index: usize) -> egui::Color32 {
+    PIN_COLORS[index % PIN_COLORS.len()]
+}
+
+// A packet kept in the panel after a click on the map or the plots
+pub struct Pin {
+    pub label: String,
+    pub packet: forza::Packet,
+}
+
+// Title and colour of a column of values
+type Header = (String, egui::Color32);
 
 #[derive(Default)]
-pub struct PacketPanel;
+pub struct PacketPanel {
+    tires: TireWidget,
+    pins: Vec<Pin>,
+}
 
 impl PacketPanel {
-    fn show_num<T: eframe::egui::emath::Numeric>(ui: &mut egui::Ui, title: &str, data: T) {
-        ui.horizontal(|ui| {
-            ui.label(title);
-            let mut data = data;
-            ui.add_enabled(false, egui::DragValue::new(&mut data));
+    pub fn pins(&self) -> &[Pin] {
+        &self.pins
+    }
+
+    // The oldest pin makes room for the new one
+    pub fn pin(&mut self, label: String, packet: &forza::Packet) {
+        let pinned = self
+            .pins
+            .iter()
+            .any(|pin| pin.label == label && pin.packet.timestamp_ms == packet.timestamp_ms);
+        if pinned {
+            return;
+        }
+        if self.pins.len() >= MAX_PINS {
+            self.pins.remove(0);
+        }
+        self.pins.push(Pin {
+            label,
+            packet: packet.clone(),
         });
     }
 
+    // The pins to remove
+    fn show_pins(ui: &mut egui::Ui, pins: &[Pin]) -> Option<std::ops::Range<usize>> {
+        if pins.is_empty() {
+            ui.label("Click the map or the plots to pin a packet");
+            return None;
+        }
+
+        let mut unpinned = None;
+        for (index, pin) in pins.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.small_button("✖").on_hover_text("Unpin").clicked() {
+                    unpinned = Some(index..index + 1);
+                }
+                ui.colored_label(pin_color(index), format!("📌{}", index + 1));
+                ui.label(&pin.label);
+            });
+        }
+        if ui.button("Clear pins").clicked() {
+            unpinned = Some(0..pins.len());
+        }
+        unpinned
+    }
+
+    // Values differing from the first column are highlighted
+    fn show_value<T: eframe::egui::emath::Numeric>(ui: &mut egui::Ui, data: T, first: T) {
+        let differs = data != first;
+        let mut data = data;
+        let value = egui::DragValue::new(&mut data);
+        match differs {
+            true => {
+                egui::Frame::none()
+                    .fill(DIFFERENCE_COLOR)
+                    .show(ui, |ui| ui.add_enabled(false, value));
+            }
+            false => {
+                ui.add_enabled(false, value);
+            }
+        }
+    }
+
+    // The names of the columns, when there are several
+    fn show_header(ui: &mut egui::Ui, headers: &[Header]) {
+        if headers.len() > 1 {
+            ui.label("");
+            for (title, color) in headers {
+                ui.colored_label(*color, title);
+            }
+            ui.end_row();
+        }
+    }
+
+    fn show_num<T: eframe::egui::emath::Numeric>(
+        ui: &mut egui::Ui,
+        title: &str,
+        packets: &[&forza::Packet],
+        data: impl Fn(&forza::Packet) -> T,
+    ) {
+        ui.label(title);
+        let first = data(packets[0]);
+        for packet in packets {
+            Self::show_value(ui, data(packet), first);
+        }
+        ui.end_row();
+    }
+
     fn show_vec3<T: eframe::egui::emath::Numeric>(
         ui: &mut egui::Ui,
         title: &str,
-        data: &forza::Vec3<T>,
+        packets: &[&forza::Packet],
+        data: impl Fn(&forza::Packet) -> forza::Vec3<T>,
     ) {
-        ui.horizontal(|ui| {
-            ui.label(title);
-            let mut data = data.clone();
-            ui.add_enabled(false, egui::DragValue::new(&mut data.x));
-            ui.add_enabled(false, egui::DragValue::new(&mut data.y));
-            ui.add_enabled(false, egui::DragValue::new(&mut data.z));
-        });
+        ui.label(title);
+        let first = data(packets[0]);
+        for packet in packets {
+            let data = data(packet);
+            ui.horizontal(|ui| {
+                Self::show_value(ui, data.x, first.x);
+                Self::show_value(ui, data.y, first.y);
+                Self::show_value(ui, data.z, first.z);
+            });
+        }
+        ui.end_row();
     }
 
     fn show_tire_stat<T: eframe::egui::emath::Numeric>(
         ui: &mut egui::Ui,
         title: &str,
-        data: &forza::TireStat<T>,
+        packets: &[&forza::Packet],
+        headers: &[Header],
+        data: impl Fn(&forza::Packet) -> forza::TireStat<T>,
     ) {
-        let mut data = data.clone();
+        let first = data(packets[0]);
         ui.separator();
         egui::Grid::new(title).show(ui, |ui| {
             ui.label(title);
-            ui.label("Left");
-            ui.label("Right");
+            for (_, color) in headers {
+                ui.colored_label(*color, "Left");
+                ui.colored_label(*color, "Right");
+            }
             ui.end_row();
 
             ui.label("Front");
-            ui.add_enabled(false, egui::DragValue::new(&mut data.front_left));
-            ui.add_enabled(false, egui::DragValue::new(&mut data.front_right));
+            for packet in packets {
+                let data = data(packet);
+                Self::show_value(ui, data.front_left, first.front_left);
+                Self::show_value(ui, data.front_right, first.front_right);
+            }
             ui.end_row();
 
             ui.label("Rear");
-            ui.add_enabled(false, egui::DragValue::new(&mut data.rear_left));
-            ui.add_enabled(false, egui::DragValue::new(&mut data.rear_right));
+            for packet in packets {
+                let data = data(packet);
+                Self::show_value(ui, data.rear_left, first.rear_left);
+                Self::show_value(ui, data.rear_right, first.rear_right);
+            }
             ui.end_row();
         });
     }
 
     pub fn show(&mut self, ctx: &CtxRef, packet: Option<&forza::Packet>) {
         let default_pkt = forza::Packet::default();
-        let pkt = packet.unwrap_or(&default_pkt);
+        let text_color = ctx.style().visuals.text_color();
+
+        // The hovered packet, then the pins side by side
+        let pins = self.pins.iter().enumerate().map(|(index, pin)| {
+            let header = (format!("📌{}", index + 1), pin_color(index));
+            (header, &pin.packet)
+        });
+        let (headers, packets): (Vec<Header>, Vec<&forza::Packet>) = packet
+            .map(|packet| (("Hovered".to_owned(), text_color), packet))
+            .into_iter()
+            .chain(pins)
+            .unzip();
+        let (headers, packets) = match packets.is_empty() {
+            true => (vec![(String::new(), text_color)], vec![&default_pkt]),
+            false => (headers, packets),
+        };
+        let pkt = packets[0];
+        let (headers, packets) = (&headers[..], &packets[..]);
+
+        let mut unpinned = None;
         egui::SidePanel::right("Packet")
             .min_width(250.0)
             .show(ctx, |ui| {
                 egui::ScrollArea::both()
                     .auto_shrink([false, false])
                     .show(ui, |ui| {
-                        Self::show_num(ui, "Is race on: ", pkt.is_race_on);
-                        Self::show_num(ui, "Timestamp: ", pkt.timestamp_ms);
-                        Self::show_vec3(
-                            ui,
-                            "Engine: ",
-                            &forza::Vec3 {
-                                x: pkt.engine_idle_rpm,
-                                y: pkt.engine_max_rpm,
-                                z: pkt.current_engine_rpm,
-                            },
-                        );
-                        Self::show_vec3(ui, "Acceleration: ", &pkt.acceleration);
-                        Self::show_vec3(ui, "Velocity: ", &pkt.velocity);
-                        Self::show_vec3(ui, "Angular velocity: ", &pkt.angular_velocity);
-                        Self::show_vec3(ui, "Rotation: ", &pkt.rotation);
+                        egui::CollapsingHeader::new("Pins")
+                            .default_open(true)
+                            .show(ui, |ui| unpinned = Self::show_pins(ui, &self.pins));
+
+                        egui::Grid::new("Packet").show(ui, |ui| {
+                            Self::show_header(ui, headers);
+                            Self::show_num(ui, "Is race on: ", packets, |p| p.is_race_on);
+                            Self::show_num(ui, "Timestamp: ", packets, |p| p.timestamp_ms);
+                            Self::show_vec3(ui, "Engine: ", packets, |p| forza::Vec3 {
+                                x: p.engine_idle_rpm,
+                                y: p.engine_max_rpm,
+                                z: p.current_engine_rpm,
+                            });
+                            Self::show_vec3(ui, "Acceleration: ", packets, |p| p.acceleration);
+                            Self::show_vec3(ui, "Velocity: ", packets, |p| p.velocity);
+                            Self::show_vec3(ui, "Angular velocity: ", packets, |p| {
+                                p.angular_velocity
+                            });
+                            Self::show_vec3(ui, "Rotation: ", packets, |p| p.rotation);
+                        });
 
                         egui::CollapsingHeader::new("Car")
                             .default_open(true)
                             .show(ui, |ui| {
-                                // Self::show_vec3(ui, "Position: ", &pkt.position);
-                                Self::show_num(ui, "Speed: ", pkt.speed);
-                                Self::show_num(ui, "Power: ", pkt.power);
-                                Self::show_num(ui, "Torque: ", pkt.torque);
-
-                                Self::show_num(ui, "Steer: ", pkt.steer);
-                                Self::show_num(ui, "Accel.: ", pkt.accel);
-                                Self::show_num(ui, "Gear: ", pkt.gear);
-                                Self::show_num(ui, "Clutch: ", pkt.clutch);
-                                Self::show_num(ui, "Brake: ", pkt.brake);
-                                Self::show_num(ui, "Hand brake: ", pkt.hand_brake);
-
-                                Self::show_num(ui, "Boost: ", pkt.boost);
-                                Self::show_num(ui, "Fuel: ", pkt.fuel);
-                                Self::show_num(ui, "Driving line: ", pkt.normalized_driving_line);
-                                Self::show_num(ui, "Airbrake: ", pkt.normalized_aibrake_difference);
+                                egui::Grid::new("Car").show(ui, |ui| {
+                                    Self::show_header(ui, headers);
+                                    // Self::show_vec3(ui, "Position: ", packets, |p| p.position);
+                                    Self::show_num(ui, "Speed: ", packets, |p| p.speed);
+                                    Self::show_num(ui, "Power: ", packets, |p| p.power);
+                                    Self::show_num(ui, "Torque: ", packets, |p| p.torque);
+
+                                    Self::show_num(ui, "Steer: ", packets, |p| p.steer);
+                                    Self::show_num(ui, "Accel.: ", packets, |p| p.accel);
+                                    Self::show_num(ui, "Gear: ", packets, |p| p.gear);
+                                    Self::show_num(ui, "Clutch: ", packets, |p| p.clutch);
+                                    Self::show_num(ui, "Brake: ", packets, |p| p.brake);
+                                    Self::show_num(ui, "Hand brake: ", packets, |p| p.hand_brake);
+
+                                    Self::show_num(ui, "Boost: ", packets, |p| p.boost);
+                                    Self::show_num(ui, "Fuel: ", packets, |p| p.fuel);
+                                    Self::show_num(ui, "Driving line: ", packets, |p| {
+                                        p.normalized_driving_line
+                                    });
+                                    Self::show_num(ui, "Airbrake: ", packets, |p| {
+                                        p.normalized_aibrake_difference
+                                    });
+                                });
 
                                 egui::CollapsingHeader::new("Vehicule")
                                     .default_open(false)
                                     .show(ui, |ui| {
-                                        Self::show_num(ui, "Ordinal: ", pkt.car_ordinal);
-                                        Self::show_num(ui, "Class: ", pkt.car_class);
-                                        Self::show_num(ui, "PI: ", pkt.car_performance_index);
-                                        Self::show_num(ui, "XWD: ", pkt.drivetrain_type);
-                                        Self::show_num(ui, "Cylinders: ", pkt.num_cylinders);
+                                        egui::Grid::new("Vehicule").show(ui, |ui| {
+                                            Self::show_header(ui, headers);
+                                            Self::show_num(ui, "Ordinal: ", packets, |p| {
+                                                p.car_ordinal
+                                            });
+                                            Self::show_num(ui, "Class: ", packets, |p| p.car_class);
+                                            Self::show_num(ui, "PI: ", packets, |p| {
+                                                p.car_performance_index
+                                            });
+                                            Self::show_num(ui, "XWD: ", packets, |p| {
+                                                p.drivetrain_type
+                                            });
+                                            Self::show_num(ui, "Cylinders: ", packets, |p| {
+                                                p.num_cylinders
+                                            });
+                                        });
                                     });
                             });
 
                         egui::CollapsingHeader::new("Race")
                             .default_open(true)
                             .show(ui, |ui| {
-                                Self::show_num(ui, "Best lap: ", pkt.best_lap);
-                                Self::show_num(ui, "Last lap: ", pkt.last_lap);
-                                Self::show_num(ui, "Current lap: ", pkt.current_lap);
-                                Self::show_num(ui, "Last number: ", pkt.lap_number);
-                                Self::show_num(ui, "Race time: ", pkt.current_race_time);
-                                Self::show_num(ui, "Position: ", pkt.race_position);
-                                Self::show_num(ui, "Distance: ", pkt.distance_traveled);
+                                egui::Grid::new("Race").show(ui, |ui| {
+                                    Self::show_header(ui, headers);
+                                    Self::show_num(ui, "Best lap: ", packets, |p| p.best_lap);
+                                    Self::show_num(ui, "Last lap: ", packets, |p| p.last_lap);
+                                    Self::show_num(ui, "Current lap: ", packets, |p| p.current_lap);
+                                    Self::show_num(ui, "Last number: ", packets, |p| p.lap_number);
+                                    Self::show_num(ui, "Race time: ", packets, |p| {
+                                        p.current_race_time
+                                    });
+                                    Self::show_num(ui, "Position: ", packets, |p| p.race_position);
+                                    Self::show_num(ui, "Distance: ", packets, |p| {
+                                        p.distance_traveled
+                                    });
+                                });
                             });
 
                         egui::CollapsingHeader::new("Wheel/Tire")
                             .default_open(true)
                             .show(ui, |ui| {
-                                Self::show_tire_stat(ui, "Rot.", &pkt.wheel_rotation_speed);
-                                Self::show_tire_stat(ui, "Temp.", &pkt.tire_temp);
-                                Self::show_tire_stat(ui, "Slip ratio", &pkt.tire_slip_ratio);
-                                Self::show_tire_stat(ui, "Slip angle", &pkt.tire_slip_angle);
-                                Self::show_tire_stat(ui, "Slip combined", &pkt.tire_combined_slip);
+                                if packet.is_some() || !self.pins.is_empty() {
+                                    self.tires.show(ui, pkt);
+                                }
+                                Self::show_tire_stat(ui, "Rot.", packets, headers, |p| {
+                                    p.wheel_rotation_speed
+                                });
+                                Self::show_tire_stat(ui, "Temp.", packets, headers, |p| {
+                                    p.tire_temp
+                                });
+                                Self::show_tire_stat(ui, "Slip ratio", packets, headers, |p| {
+                                    p.tire_slip_ratio
+                                });
+                                Self::show_tire_stat(ui, "Slip angle", packets, headers, |p| {
+                                    p.tire_slip_angle
+                                });
+                                Self::show_tire_stat(ui, "Slip combined", packets, headers, |p| {
+                                    p.tire_combined_slip
+                                });
                             });
 
                         egui::CollapsingHeader::new("Suspensions")
                             .default_open(true)
                             .show(ui, |ui| {
-                                Self::show_tire_stat(
-                                    ui,
-                                    "Normalized",
-                                    &pkt.normalized_suspension_travel,
-                                );
-                                Self::show_tire_stat(ui, "Travel", &pkt.suspension_travel);
+                                Self::show_tire_stat(ui, "Normalized", packets, headers, |p| {
+                                    p.normalized_suspension_travel
+                                });
+                                Self::show_tire_stat(ui, "Travel", packets, headers, |p| {
+                                    p.suspension_travel
+                                });
                             });
 
                         egui::CollapsingHeader::new("Surface")
                             .default_open(true)
                             .show(ui, |ui| {
-                                Self::show_tire_stat(ui, "Rumble", &pkt.surface_rumble);
-                                Self::show_tire_stat(ui, "Strip", &pkt.wheel_on_rumble_strip);
-                                Self::show_tire_stat(ui, "Puddle", &pkt.wheel_in_puddle_depth);
+                                Self::show_tire_stat(ui, "Rumble", packets, headers, |p| {
+                                    p.surface_rumble
+                                });
+                                Self::show_tire_stat(ui, "Strip", packets, headers, |p| {
+                                    p.wheel_on_rumble_strip
+                                });
+                                Self::show_tire_stat(ui, "Puddle", packets, headers, |p| {
+                                    p.wheel_in_puddle_depth
+                                });
                             });
                     });
             });
+        if let Some(range) = unpinned {
+            self.pins.drain(range);
+        }
     }
 }
@@ -0,0 +1,109 @@
+use crate::forza::{self, ChunkSelector};
+use crate::gui::format_time;
+use eframe::egui;
+
+const SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+
+pub struct PlaybackPanel {
+    active: bool, // The cursor replaces the mouse hover
+    playing: bool,
+    speed: f32,
+    position: f32, // seconds since the first packet
+    selection: Option<ChunkSelector>,
+}
+
+impl Default for PlaybackPanel {
+    fn default() -> Self {
+        Self {
+            active: false,
+            playing: false,
+            speed: 1.0,
+            position: 0.0,
+            selection: None,
+        }
+    }
+}
+
+fn time_of(first: &forza::Packet, packet: &forza::Packet) -> f32 {
+    packet.timestamp_ms.saturating_sub(first.timestamp_ms) as f32 / 1000.0
+}
+
+impl PlaybackPanel {
+    // The packet under the playback cursor, while playing or scrubbing
+    pub fn cursor(&self, packets: &[forza::Packet]) -> Option<usize> {
+        let first = packets.first().filter(|_| self.active)?;
+        let index = packets.partition_point(|p| time_of(first, p) <= self.position);
+        Some(index.saturating_sub(1))
+    }
+
+    pub fn show(&mut self, ctx: &egui::CtxRef, chunks: &forza::Chunks, selection: ChunkSelector) {
+        let packets = chunks.packets_of(selection);
+        if self.selection != Some(selection) {
+            self.selection = Some(selection);
+            self.playing = false;
+            self.position = 0.0;
+        }
+
+        let duration = match (packets.first(), packets.last()) {
+            (Some(first), Some(last)) => time_of(first, last),
+            _ => 0.0,
+        };
+
+        if self.playing {
+            let dt = ctx.input().unstable_dt.min(0.1);
+            self.position += dt * self.speed;
+            if self.position >= duration {
+                self.position = duration;
+                self.playing = false;
+            }
+            ctx.request_repaint();
+        }
+
+        egui::Window::new("Playback").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let play = match self.playing {
+                    true => "⏸",
+                    false => "▶",
+                };
+                if ui.button(play).clicked() {
+                    if !self.playing && self.position >= duration {
+                        self.position = 0.0;
+                    }
+                    self.playing = !self.playing;
+                    self.active = true;
+                }
+                if ui
+                    .add_enabled(self.active, egui::Button::new("⏹"))
+                    .on_hover_text("Follow the mouse again")
+                    .clicked()
+                {
+                    self.playing = false;
+                    self.active = false;
+                    self.position = 0.0;
+                }
+
+                egui::ComboBox::from_id_source("Playback speed")
+                    .selected_text(format!("{}×", self.speed))
+                    .width(60.0)
+                    .show_ui(ui, |ui| {
+                        for speed in SPEEDS {
+                            ui.selectable_value(&mut self.speed, speed, format!("{}×", speed));
+                        }
+                    });
+            });
+
+            ui.horizontal(|ui| {
+                let slider = egui::Slider::new(&mut self.position, 0.0..=duration.max(0.001))
+                    .show_value(false);
+                if ui.add(slider).changed() {
+                    self.active = true;
+                }
+                ui.label(format!(
+                    "{} / {}",
+                    format_time(self.position),
+                    format_time(duration)
+                ));
+            });
+        });
+    }
+}
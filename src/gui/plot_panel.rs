@@ -0,0 +1,195 @@
+use crate::forza::{self, ChunkSelector, Comparison};
+use eframe::egui;
+
+use egui::plot::{self, Value, Values};
+
+const MAX_POINTS: usize = 5000; // Per line, to keep the plots fluid
+const MIN_VIEW: f32 = 0.5; // Narrowest zoom, in seconds or meters
+
+#[derive(PartialEq, Clone, Copy)]
+enum Axis {
+    Time,
+    Distance,
+}
+
+pub struct PlotPanel {
+    channels: Vec<forza::Channel>,
+    axis: Axis,
+    xs: Vec<f32>, // Time or distance of each selected packet
+    xs_key: Option<(ChunkSelector, usize, bool)>,
+    view: Option<(f32, f32)>, // The range of x every lane shows, None for all of it
+    lanes: Option<egui::Rect>, // Where the lanes were last drawn
+    hovered: Option<usize>,   // Packet under the mouse in the plots
+    cursor: Option<usize>,    // Packet to mark, hovered on the map or played back
+    clicked: Option<usize>,
+}
+
+impl Default for PlotPanel {
+    fn default() -> Self {
+        Self {
+            channels: vec![
+                forza::Channel::Speed,
+                forza::Channel::Throttle,
+                forza::Channel::Brake,
+            ],
+            axis: Axis::Time,
+            xs: Vec::new(),
+            xs_key: None,
+            view: None,
+            lanes: None,
+            hovered: None,
+            cursor: None,
+            clicked: None,
+        }
+    }
+}
+
+impl PlotPanel {
+    pub fn hovered(&self) -> Option<usize> {
+        self.hovered
+    }
+
+    // The packet under the mouse when the plots were clicked in this frame
+    pub fn clicked(&self) -> Option<usize> {
+        self.clicked
+    }
+
+    // The packet to mark when the plots are not hovered
+    pub fn set_cursor(&mut self, packet_index: Option<usize>) {
+        self.cursor = packet_index;
+    }
+
+    fn update_xs(&mut self, selection: ChunkSelector, packets: &[forza::Packet]) {
+        let key = Some((selection, packets.len(), self.axis == Axis::Time));
+        if key == self.xs_key {
+            return;
+        }
+        // The view stays while recording, but not on another selection or axis
+        if self.xs_key.map(|k| (k.0, k.2)) != key.map(|k| (k.0, k.2)) {
+            self.view = None;
+        }
+        self.xs_key = key;
+
+        self.xs = match (self.axis, packets.first()) {
+            (Axis::Time, Some(first)) => packets
+                .iter()
+                .map(|p| p.timestamp_ms.saturating_sub(first.timestamp_ms) as f32 / 1000.0)
+                .collect(),
+            (Axis::Distance, _) => Comparison::distances(packets),
+            (_, None) => Vec::new(),
+        };
+    }
+
+    fn show_channels(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.axis, Axis::Time, "Time");
+            ui.selectable_value(&mut self.axis, Axis::Distance, "Distance");
+        });
+
+        ui.collapsing("Channels", |ui| {
+            for channel in forza::Channel::ALL {
+                let mut checked = self.channels.contains(&channel);
+                if ui.checkbox(&mut checked, channel.name()).changed() {
+                    match checked {
+                        true => self.channels.push(channel),
+                        false => self.channels.retain(|c| *c != channel),
+                    }
+                }
+            }
+        });
+    }
+
+    pub fn show(&mut self, ctx: &egui::CtxRef, chunks: &forza::Chunks, selection: ChunkSelector) {
+        let packets = chunks.packets_of(selection);
+
+        self.clicked = None;
+        egui::Window::new("Plots").show(ctx, |ui| {
+            self.show_channels(ui);
+            self.update_xs(selection, packets);
+            ui.separator();
+
+            let xs = &self.xs;
+            let all = match (xs.first(), xs.last()) {
+                (Some(first), Some(last)) => (*first, last.max(first + MIN_VIEW)),
+                _ => (0.0, 1.0),
+            };
+            let view = self.view.unwrap_or(all);
+            let visible = xs.partition_point(|x| *x < view.0)..xs.partition_point(|x| *x <= view.1);
+            let step_by = 1 + visible.len() / MAX_POINTS;
+
+            // The lanes share the x axis, so the pointer gives the x of all of them,
+            // even of the lanes drawn before the hovered one
+            let lanes = self.lanes;
+            let pointer = ui
+                .input()
+                .pointer
+                .hover_pos()
+                .filter(|pos| lanes.is_some_and(|lanes| lanes.contains(*pos)))
+                .filter(|pos| ui.ctx().layer_id_at(*pos) == Some(ui.layer_id()));
+            let cursor = self.cursor.and_then(|index| xs.get(index)).copied();
+
+            let mut hovered_x = None;
+            let mut new_view = view;
+            let mut lanes_rect: Option<egui::Rect> = None;
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for channel in &self.channels {
+                    ui.label(format!("{} {}", channel.name(), channel.unit()));
+
+                    let values = xs[visible.clone()]
+                        .iter()
+                        .zip(&packets[visible.clone()])
+                        .step_by(step_by)
+                        .map(|(x, p)| Value::new(*x, channel.value(p)));
+                    let line = plot::Line::new(Values::from_values_iter(values))
+                        .color(egui::Color32::from_rgb(255, 48, 134));
+
+                    let mut pointer_x = None;
+                    let response = plot::Plot::new(channel.name())
+                        .height(90.0)
+                        .allow_drag(false)
+                        .allow_zoom(false)
+                        .include_x(view.0)
+                        .include_x(view.1)
+                        .show(ui, |plot_ui| {
+                            pointer_x = pointer.map(|pos| plot_ui.plot_from_screen(pos).x as f32);
+                            plot_ui.line(line);
+                            if let Some(x) = pointer_x.or(cursor) {
+                                plot_ui.vline(plot::VLine::new(x).color(egui::Color32::WHITE));
+                            }
+                        });
+                    hovered_x = hovered_x.or(pointer_x);
+                    lanes_rect = Some(lanes_rect.map_or(response.rect, |r| r.union(response.rect)));
+
+                    // Every lane zooms and pans all of them
+                    let width = view.1 - view.0;
+                    if response.hovered() {
+                        let zoom = ui.input().zoom_delta();
+                        let center = pointer_x.unwrap_or((view.0 + view.1) / 2.0);
+                        let scale = |edge: f32| center + (edge - center) / zoom;
+                        new_view = (scale(view.0), scale(view.1));
+                    }
+                    if response.dragged() {
+                        let shift = -response.drag_delta().x / response.rect.width() * width;
+                        new_view = (view.0 + shift, view.1 + shift);
+                    }
+                    if response.double_clicked() {
+                        new_view = all;
+                    }
+                }
+            });
+            self.lanes = lanes_rect;
+
+            // Stay within the selection
+            let width = (new_view.1 - new_view.0).max(MIN_VIEW).min(all.1 - all.0);
+            let start = new_view.0.min(all.1 - width).max(all.0);
+            self.view = Some((start, start + width)).filter(|view| *view != all);
+
+            self.hovered = hovered_x
+                .map(|x| xs.partition_point(|packet_x| *packet_x < x))
+                .filter(|index| *index < xs.len());
+            if ui.input().pointer.any_click() {
+                self.clicked = self.hovered;
+            }
+        });
+    }
+}
@@ -0,0 +1,152 @@
+use crate::forza::{self, Channel, Wheel};
+use crate::gui::ColorRamp;
+use eframe::egui;
+
+use egui::{Align2, Color32, Pos2, Rect, Stroke, TextStyle, Vec2};
+
+const CHANNELS: [Channel; 4] = [
+    Channel::TireTemp,
+    Channel::SlipRatio,
+    Channel::SlipAngle,
+    Channel::CombinedSlip,
+];
+const TIRE_TEMP_RANGE: (f32, f32) = (100.0, 260.0); // °F, from cold to overheating
+
+const WHEEL_SIZE: Vec2 = Vec2::new(24.0, 42.0);
+const BAR_WIDTH: f32 = 6.0;
+const PUDDLE_COLOR: Color32 = Color32::from_rgb(60, 140, 255);
+const WARNING_COLOR: Color32 = Color32::from_rgb(255, 40, 40);
+
+// The four corners of the car, coloured by a tire channel
+pub struct TireWidget {
+    channel: Channel,
+}
+
+impl Default for TireWidget {
+    fn default() -> Self {
+        Self {
+            channel: Channel::TireTemp,
+        }
+    }
+}
+
+impl TireWidget {
+    // Position of the wheel in the car, -1 for left or front
+    fn side(wheel: Wheel) -> (f32, f32) {
+        match wheel {
+            Wheel::FrontLeft => (-1.0, -1.0),
+            Wheel::FrontRight => (1.0, -1.0),
+            Wheel::RearLeft => (-1.0, 1.0),
+            Wheel::RearRight => (1.0, 1.0),
+        }
+    }
+
+    fn color(&self, value: f32) -> Color32 {
+        let t = match self.channel {
+            Channel::TireTemp => {
+                let (cold, hot) = TIRE_TEMP_RANGE;
+                (value - cold) / (hot - cold)
+            }
+            _ => value.abs(),
+        };
+        ColorRamp::Heat.color(t)
+    }
+
+    fn text(&self, value: f32) -> String {
+        match self.channel {
+            Channel::TireTemp => format!("{:.0}°", value),
+            _ => format!("{:.2}", value),
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, packet: &forza::Packet) {
+        egui::ComboBox::from_id_source(ui.id().with("Tire channel"))
+            .selected_text(self.channel.name())
+            .show_ui(ui, |ui| {
+                for channel in CHANNELS {
+                    ui.selectable_value(&mut self.channel, channel, channel.name());
+                }
+            });
+
+        let (response, painter) =
+            ui.allocate_painter(Vec2::new(180.0, 170.0), egui::Sense::hover());
+        let center = response.rect.center();
+        let body = Rect::from_center_size(center, Vec2::new(56.0, 130.0));
+        painter.rect_stroke(body, 12.0, ui.visuals().widgets.noninteractive.fg_stroke);
+
+        // Blink twice a second when a tire loses grip
+        let grip_lost = Wheel::ALL
+            .iter()
+            .any(|w| w.of(&packet.tire_combined_slip).abs() > 1.0);
+        let blink = ui.input().time.fract() < 0.5;
+        if grip_lost {
+            ui.ctx().request_repaint();
+        }
+
+        for wheel in Wheel::ALL {
+            let (x, y) = Self::side(wheel);
+            let wheel_center =
+                center + Vec2::new(x * (body.width() / 2.0 + 4.0), y * body.height() / 3.0);
+            let rect = Rect::from_center_size(wheel_center, WHEEL_SIZE);
+
+            let value = self.channel.wheel_value(packet, wheel).unwrap_or_default();
+            painter.rect_filled(rect, 4.0, self.color(value));
+            if wheel.of(&packet.tire_combined_slip).abs() > 1.0 && blink {
+                painter.rect_stroke(rect.expand(3.0), 6.0, Stroke::new(3.0, WARNING_COLOR));
+            }
+            if wheel.of(&packet.wheel_on_rumble_strip) != 0 {
+                for i in 1..4 {
+                    let stripe_y = rect.top() + rect.height() * i as f32 / 4.0;
+                    painter.line_segment(
+                        [
+                            Pos2::new(rect.left(), stripe_y),
+                            Pos2::new(rect.right(), stripe_y),
+                        ],
+                        Stroke::new(2.0, Color32::WHITE),
+                    );
+                }
+            }
+
+            // Suspension bar on the outer side, filled with the compression
+            let bar_x = rect.center().x + x * (WHEEL_SIZE.x / 2.0 + 4.0 + BAR_WIDTH / 2.0);
+            let bar = Rect::from_center_size(
+                Pos2::new(bar_x, rect.center().y),
+                Vec2::new(BAR_WIDTH, WHEEL_SIZE.y),
+            );
+            let travel = wheel
+                .of(&packet.normalized_suspension_travel)
+                .clamp(0.0, 1.0);
+            let mut fill = bar;
+            fill.set_top(bar.bottom() - bar.height() * travel);
+            painter.rect_filled(bar, 1.0, ui.visuals().extreme_bg_color);
+            painter.rect_filled(fill, 1.0, ui.visuals().text_color());
+
+            let puddle = wheel.of(&packet.wheel_in_puddle_depth);
+            if puddle > 0.0 {
+                let puddle_center = Pos2::new(rect.center().x, rect.center().y + y * 30.0);
+                painter.circle_filled(puddle_center, 3.0 + 3.0 * puddle.min(1.0), PUDDLE_COLOR);
+            }
+
+            let text_x = bar_x + x * (BAR_WIDTH / 2.0 + 4.0);
+            let anchor = match x < 0.0 {
+                true => Align2::RIGHT_CENTER,
+                false => Align2::LEFT_CENTER,
+            };
+            painter.text(
+                Pos2::new(text_x, rect.center().y),
+                anchor,
+                self.text(value),
+                TextStyle::Small,
+                ui.visuals().text_color(),
+            );
+        }
+
+        if grip_lost {
+            let color = match blink {
+                true => WARNING_COLOR,
+                false => ui.visuals().weak_text_color(),
+            };
+            ui.colored_label(color, "⚠ Grip lost");
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use crate::event::{self, EventGenerator};
+use crate::forza::{self, TrackId};
+use crate::gui::format_time;
+use eframe::egui;
+
+pub enum EventTypes {
+    SetActive(Option<TrackId>),
+    Create(String),
+    Rename(TrackId, String),
+    Remove(TrackId),
+    AssignToSelection(Option<TrackId>),
+    SetReference(TrackId),
+    ClearSectors(TrackId),
+    ResetBests(TrackId),
+}
+type Events = event::Events<EventTypes>;
+
+#[derive(Default)]
+pub struct TrackPanel {
+    active: Option<TrackId>,
+    name: String,
+    events: Events,
+}
+
+impl event::EventGenerator<EventTypes> for TrackPanel {
+    fn events(&mut self) -> &mut Events {
+        &mut self.events
+    }
+}
+
+impl TrackPanel {
+    pub fn active(&self) -> Option<TrackId> {
+        self.active
+    }
+
+    pub fn set_active(&mut self, track_id: Option<TrackId>) {
+        self.active = track_id;
+    }
+
+    pub fn show(&mut self, ctx: &egui::CtxRef, tracks: &forza::Tracks) {
+        egui::Window::new("Tracks").show(ctx, |ui| {
+            let active_name = self
+                .active
+                .and_then(|track_id| tracks.get(track_id))
+                .map_or("None", |track| &track.name);
+
+            egui::ComboBox::from_label("Active track")
+                .selected_text(active_name)
+                .show_ui(ui, |ui| {
+                    if ui.selectable_label(self.active.is_none(), "None").clicked() {
+                        self.gen_event(EventTypes::SetActive(None));
+                    }
+                    for (track_id, track) in tracks.iter() {
+                        if ui
+                            .selectable_label(self.active == Some(track_id), &track.name)
+                            .clicked()
+                        {
+                            self.gen_event(EventTypes::SetActive(Some(track_id)));
+                        }
+                    }
+                });
+
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.name);
+                let has_name = !self.name.trim().is_empty();
+                if ui.add_enabled(has_name, egui::Button::new("New")).clicked() {
+                    self.gen_event(EventTypes::Create(self.name.trim().to_owned()));
+                    self.name.clear();
+                }
+                if let Some(track_id) = self.active {
+                    if ui
+                        .add_enabled(has_name, egui::Button::new("Rename"))
+                        .clicked()
+                    {
+                        self.gen_event(EventTypes::Rename(track_id, self.name.trim().to_owned()));
+                        self.name.clear();
+                    }
+                }
+            });
+
+            let (track_id, track) = match self.active.and_then(|id| Some((id, tracks.get(id)?))) {
+                Some(active) => active,
+                None => return,
+            };
+
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Assign to selection")
+                    .on_hover_text("Time the sectors of the selected chunk on this track")
+                    .clicked()
+                {
+                    self.gen_event(EventTypes::AssignToSelection(Some(track_id)));
+                }
+                if ui.button("Unassign").clicked() {
+                    self.gen_event(EventTypes::AssignToSelection(None));
+                }
+                if ui.button("🗑").on_hover_text("Delete the track").clicked() {
+                    self.gen_event(EventTypes::Remove(track_id));
+                }
+            });
+            ui.horizontal(|ui| {
+                match track.reference.len() {
+                    0 => ui.label("No reference path"),
+                    len => ui.label(format!("Reference path: {:.1} km", len as f32 / 100.0)),
+                };
+                if ui
+                    .button("Use selection")
+                    .on_hover_text("Identify the races following the selected lap")
+                    .clicked()
+                {
+                    self.gen_event(EventTypes::SetReference(track_id));
+                }
+            });
+            ui.separator();
+
+            self.show_bests(ui, track_id, track);
+        });
+    }
+
+    fn show_bests(&mut self, ui: &mut egui::Ui, track_id: TrackId, track: &forza::Track) {
+        let time_text = |time: Option<f32>| time.map_or("-".to_owned(), format_time);
+
+        egui::Grid::new("Bests").num_columns(2).show(ui, |ui| {
+            if !track.sectors.is_empty() {
+                for (sector_num, best) in track.best_sectors.iter().enumerate() {
+                    ui.label(format!("Sector {}", sector_num + 1));
+                    ui.label(time_text(*best));
+                    ui.end_row();
+                }
+
+                ui.label("Theoretical best");
+                ui.label(time_text(track.theoretical_best()));
+                ui.end_row();
+            }
+
+            ui.label("Best lap");
+            ui.label(time_text(track.best_lap));
+            ui.end_row();
+        });
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    !track.sectors.is_empty(),
+                    egui::Button::new("Clear sectors"),
+                )
+                .clicked()
+            {
+                self.gen_event(EventTypes::ClearSectors(track_id));
+            }
+            if ui.button("Reset bests").clicked() {
+                self.gen_event(EventTypes::ResetBests(track_id));
+            }
+        });
+    }
+}